@@ -0,0 +1,490 @@
+//! `zcleaner-cli`: the scan, clean, disk, system and process commands of the
+//! app without a window, for scripts and SSH sessions.
+
+use serde::Serialize;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use zcleaner_core::cleaning::DeleteControl;
+use zcleaner_core::filesystem::OsFs;
+use zcleaner_core::filters::{now_millis, EntryFilters};
+use zcleaner_core::i18n::{Catalog, Locale};
+use zcleaner_core::quarantine::Quarantine;
+use zcleaner_core::report::{failure_name, format_size, outcome_name, rejection_name};
+use zcleaner_core::roots::RootResolver;
+use zcleaner_core::rules::{Platform, RuleFamily, RuleRegistry};
+use zcleaner_core::scan::ScanControl;
+use zcleaner_core::session::{RejectedPath, ScanSession};
+use zcleaner_core::{
+    delete_entries, disk_info, list_processes, plan_entries, scan_entries, system_info,
+    terminate_process, ScanResult, TempFile,
+};
+
+/// `println!` that doesn't panic when stdout is closed early, as with
+/// `zcleaner-cli ps | head`.
+macro_rules! out {
+    ($($arg:tt)*) => {{
+        use std::io::Write;
+        let _ = writeln!(std::io::stdout(), $($arg)*);
+    }};
+}
+
+/// Must match `identifier` in tauri.conf.json, so the CLI reads the same
+/// rule and exclusion files as the app.
+const APP_IDENTIFIER: &str = "com.admininastro.zcleaner";
+
+const USAGE: &str = "\
+Usage: zcleaner-cli [--json] [--lang pt-BR|en|es] <command> [options]
+
+Commands:
+  scan     [--category ID,...] [--family system,browser,developer] [--older-than DAYS]
+  clean    --category ID,... [--family ...] [--older-than DAYS] [--include-in-use]
+           [--dry-run] [--quarantine]
+  quarantine [list | restore ID... | purge [ID...]]
+  disks
+  sysinfo
+  ps
+  kill     PID
+
+Options:
+  --json      Print the result as JSON instead of a table
+  --lang      Language of category names and messages (default: from LANG)
+  --root DIR  Resolve rule paths below DIR, e.g. a mounted Windows volume;
+              variables such as LOCALAPPDATA still come from the environment
+  --platform  windows|linux|macos: whose rules apply (default: this OS)
+";
+
+/// Command-line arguments after the program name.
+struct Args {
+    json: bool,
+    locale: Locale,
+    command: String,
+    categories: Vec<String>,
+    families: Vec<RuleFamily>,
+    older_than: Option<u32>,
+    include_in_use: bool,
+    dry_run: bool,
+    quarantine: bool,
+    root: Option<PathBuf>,
+    platform: Option<Platform>,
+    positional: Vec<String>,
+}
+
+impl Args {
+    fn parse(mut raw: impl Iterator<Item = String>) -> Result<Args, String> {
+        let mut args = Args {
+            json: false,
+            locale: locale_from_env(),
+            command: String::new(),
+            categories: Vec::new(),
+            families: Vec::new(),
+            older_than: None,
+            include_in_use: false,
+            dry_run: false,
+            quarantine: false,
+            root: None,
+            platform: None,
+            positional: Vec::new(),
+        };
+        while let Some(arg) = raw.next() {
+            let mut value = |name: &str| {
+                raw.next()
+                    .ok_or_else(|| format!("Faltou o valor de {}", name))
+            };
+            match arg.as_str() {
+                "--json" => args.json = true,
+                "--include-in-use" => args.include_in_use = true,
+                "--dry-run" => args.dry_run = true,
+                "--quarantine" => args.quarantine = true,
+                "--lang" => {
+                    let lang = value("--lang")?;
+                    args.locale = serde_json::from_value(lang.clone().into())
+                        .map_err(|_| format!("Idioma desconhecido: {}", lang))?;
+                }
+                "--category" => args.categories.extend(split_list(&value("--category")?)),
+                "--family" => {
+                    for family in split_list(&value("--family")?) {
+                        args.families.push(
+                            serde_json::from_value(family.clone().into())
+                                .map_err(|_| format!("Família desconhecida: {}", family))?,
+                        );
+                    }
+                }
+                "--older-than" => {
+                    let days = value("--older-than")?;
+                    args.older_than = Some(
+                        days.parse()
+                            .map_err(|_| format!("Número de dias inválido: {}", days))?,
+                    );
+                }
+                "--root" => args.root = Some(PathBuf::from(value("--root")?)),
+                "--platform" => {
+                    let platform = value("--platform")?;
+                    args.platform = Some(
+                        serde_json::from_value(platform.clone().into())
+                            .map_err(|_| format!("Plataforma desconhecida: {}", platform))?,
+                    );
+                }
+                other if other.starts_with("--") => {
+                    return Err(format!("Opção desconhecida: {}", other))
+                }
+                _ if args.command.is_empty() => args.command = arg,
+                _ => args.positional.push(arg),
+            }
+        }
+        Ok(args)
+    }
+
+    fn registry(&self) -> Result<RuleRegistry, String> {
+        let mut resolver = RootResolver::default();
+        if let Some(root) = &self.root {
+            resolver = resolver.prefix(root);
+        }
+        if let Some(platform) = self.platform {
+            resolver = resolver.platform(platform);
+        }
+        let mut registry = RuleRegistry::load_with(config_dir().as_deref(), resolver)?;
+        if self.families.is_empty() {
+            registry.retain_families(&RuleFamily::DEFAULT_SCAN);
+        } else {
+            registry.retain_families(&self.families);
+        }
+        if !self.categories.is_empty() {
+            registry.retain_categories(&self.categories);
+        }
+        registry.localize(Catalog::get(self.locale));
+        Ok(registry)
+    }
+
+    fn filters(&self) -> EntryFilters {
+        EntryFilters {
+            min_age_days: self.older_than,
+            ..EntryFilters::default()
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) if !args.command.is_empty() => args,
+        Ok(_) => {
+            eprint!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    let catalog = Catalog::get(args.locale);
+
+    let result = match args.command.as_str() {
+        "scan" => scan(&args).and_then(|result| {
+            print(&args, &result, || print_scan(catalog, &result.files, result.total_size))
+        }),
+        "clean" => clean(&args, catalog),
+        "quarantine" => quarantine(&args, catalog),
+        "disks" => {
+            let disks = disk_info();
+            print(&args, &disks, || {
+                for disk in &disks {
+                    out!(
+                        "{:<20} {:<30} {:<8} {:>10} / {:>10}",
+                        disk.name,
+                        disk.mount_point,
+                        disk.file_system,
+                        format_size(disk.available_space),
+                        format_size(disk.total_space)
+                    );
+                }
+            })
+        }
+        "sysinfo" => {
+            let info = system_info(catalog);
+            print(&args, &info, || {
+                out!("{}: {} {}", catalog.message("report-os"), info.os_name, info.os_version);
+                out!("{}: {}", catalog.message("report-kernel"), info.kernel_version);
+                out!("{}: {} ({})", catalog.message("report-cpu"), info.cpu_name, info.cpu_cores);
+                out!(
+                    "{}: {} / {}",
+                    catalog.message("report-memory"),
+                    format_size(info.used_memory),
+                    format_size(info.total_memory)
+                );
+            })
+        }
+        "ps" => {
+            let mut processes = list_processes(catalog);
+            processes.sort_by_key(|p| p.pid);
+            print(&args, &processes, || {
+                for process in &processes {
+                    out!(
+                        "{:>8} {:<32} {:>6.1}% {:>10} {}",
+                        process.pid,
+                        process.name,
+                        process.cpu_usage,
+                        format_size(process.memory),
+                        process.status
+                    );
+                }
+            })
+        }
+        "kill" => match args.positional.first().map(|pid| pid.parse::<u32>()) {
+            Some(Ok(pid)) => terminate_process(catalog, pid)
+                .and_then(|killed| print(&args, &killed, || out!("{}", pid))),
+            _ => Err("Informe o PID do processo".to_string()),
+        },
+        other => Err(format!("Comando desconhecido: {}\n\n{}", other, USAGE)),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("{}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn scan(args: &Args) -> Result<ScanResult, String> {
+    let registry = args.registry()?;
+    let files = scan_entries(&registry, &args.filters(), &ScanControl::default(), &());
+    Ok(ScanResult {
+        // Sessions only live inside the app.
+        session_id: String::new(),
+        total_size: files.iter().map(|f| f.size).sum(),
+        files,
+        cancelled: false,
+        scanned_at: Some(now_millis()),
+    })
+}
+
+/// Scans the chosen categories and deletes everything found, with the same
+/// checks as `delete_temp_files`. With `--dry-run` only prints what would be
+/// removed; with `--quarantine` moves it into the app's quarantine.
+fn clean(args: &Args, catalog: &Catalog) -> Result<(), String> {
+    if args.categories.is_empty() {
+        return Err("Informe as categorias a limpar com --category".to_string());
+    }
+    let registry = args.registry()?;
+    let request = args.filters();
+    let files = scan_entries(&registry, &request, &ScanControl::default(), &());
+    let session = ScanSession::new(&files);
+    let paths: Vec<String> = files.into_iter().map(|f| f.path).collect();
+    if args.dry_run {
+        let plan = plan_entries(&registry, &session, &paths, &request, args.include_in_use);
+        return print(args, &plan, || {
+            let planned: Vec<TempFile> = plan
+                .planned
+                .iter()
+                .map(|p| TempFile {
+                    size: p.removal.bytes,
+                    ..p.entry.clone()
+                })
+                .collect();
+            print_scan(catalog, &planned, planned.iter().map(|f| f.size).sum());
+            let failures: Vec<_> = plan
+                .planned
+                .iter()
+                .flat_map(|p| &p.removal.failures)
+                .collect();
+            if !failures.is_empty() {
+                out!("\n{}:", catalog.message("report-failures"));
+                for failure in failures {
+                    out!("  {} ({})", failure.path, failure_name(failure.kind));
+                }
+            }
+            print_rejected(catalog, &plan.rejected);
+        });
+    }
+    let mut quarantine = if args.quarantine {
+        Some(open_quarantine()?)
+    } else {
+        None
+    };
+    let result = delete_entries(
+        &registry,
+        &session,
+        &paths,
+        &request,
+        args.include_in_use,
+        quarantine.as_mut(),
+        &DeleteControl::default(),
+    );
+
+    print(args, &result, || {
+        print_scan(catalog, &result.deleted, result.deleted_size);
+        let failed: Vec<_> = result
+            .items
+            .iter()
+            .filter(|item| item.outcome.is_incomplete())
+            .collect();
+        if !failed.is_empty() {
+            out!("\n{}:", catalog.message("report-failed"));
+            for item in failed {
+                let reason = item.error.map(failure_name).unwrap_or_default();
+                out!("  {} ({}, {})", item.path, outcome_name(item.outcome), reason);
+                for failure in item.failures.iter().filter(|f| f.path != item.path) {
+                    out!("    {} ({})", failure.path, failure_name(failure.kind));
+                }
+            }
+        }
+        print_rejected(catalog, &result.rejected);
+    })
+}
+
+/// Lists the quarantine, or restores or purges the items given by id. `purge`
+/// without ids empties it.
+fn quarantine(args: &Args, catalog: &Catalog) -> Result<(), String> {
+    let (action, ids) = match args.positional.split_first() {
+        Some((action, ids)) => (action.as_str(), ids),
+        None => ("list", &[][..]),
+    };
+    match action {
+        "list" => {
+            let list = Quarantine::list_in(&quarantine_data_dir()?)?;
+            print(args, &list, || {
+                for item in &list.items {
+                    out!(
+                        "{}  {:>10}  {}",
+                        item.id,
+                        format_size(item.entry.size),
+                        item.entry.path
+                    );
+                }
+                out!(
+                    "{:<36}  {:>10}",
+                    catalog.message("report-total"),
+                    format_size(list.total_size)
+                );
+            })
+        }
+        "restore" if ids.is_empty() => Err("Informe os itens a restaurar".to_string()),
+        "restore" => {
+            let result = open_quarantine()?.restore(&OsFs, ids, catalog);
+            print(args, &result, || {
+                for item in &result.restored {
+                    out!("{}", item.entry.path);
+                }
+                for failure in &result.failed {
+                    eprintln!("{}: {}", failure.id, failure.reason);
+                }
+            })
+        }
+        "purge" => {
+            let ids = (!ids.is_empty()).then_some(ids);
+            let result = open_quarantine()?.purge(&OsFs, ids);
+            print(args, &result, || {
+                for item in &result.purged {
+                    out!("{}", item.entry.path);
+                }
+                out!(
+                    "{}: {}",
+                    catalog.message("report-total"),
+                    format_size(result.freed)
+                );
+            })
+        }
+        other => Err(format!("Ação desconhecida: {}\n\n{}", other, USAGE)),
+    }
+}
+
+fn print_rejected(catalog: &Catalog, rejected: &[RejectedPath]) {
+    if !rejected.is_empty() {
+        out!("\n{}:", catalog.message("report-rejected"));
+        for rejected in rejected {
+            out!("  {} ({})", rejected.path, rejection_name(rejected.reason));
+        }
+    }
+}
+
+/// Per-category totals of `files`.
+fn print_scan(catalog: &Catalog, files: &[TempFile], total_size: u64) {
+    let mut totals: Vec<(&str, usize, u64)> = Vec::new();
+    for file in files {
+        match totals.iter_mut().find(|(name, _, _)| *name == file.category) {
+            Some(total) => {
+                total.1 += 1;
+                total.2 += file.size;
+            }
+            None => totals.push((&file.category, 1, file.size)),
+        }
+    }
+    for (name, count, size) in totals {
+        out!("{:<40} {:>8} {:>10}", name, count, format_size(size));
+    }
+    out!(
+        "{:<40} {:>8} {:>10}",
+        catalog.message("report-total"),
+        files.len(),
+        format_size(total_size)
+    );
+}
+
+fn print<T: Serialize>(args: &Args, value: &T, human: impl FnOnce()) -> Result<(), String> {
+    if args.json {
+        let json = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+        out!("{}", json);
+    } else {
+        human();
+    }
+    Ok(())
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Locale from `LANG`/`LC_ALL`, pt-BR when it isn't English or Spanish.
+fn locale_from_env() -> Locale {
+    let lang = std::env::var("LC_ALL")
+        .or_else(|_| std::env::var("LANG"))
+        .unwrap_or_default();
+    if lang.starts_with("en") {
+        Locale::En
+    } else if lang.starts_with("es") {
+        Locale::Es
+    } else {
+        Locale::PtBr
+    }
+}
+
+/// The directory Tauri resolves as `app_config_dir`.
+fn config_dir() -> Option<PathBuf> {
+    app_dir("XDG_CONFIG_HOME", ".config")
+}
+
+/// The directory Tauri resolves as `app_data_dir`.
+fn data_dir() -> Option<PathBuf> {
+    app_dir("XDG_DATA_HOME", ".local/share")
+}
+
+/// `$APPDATA` on Windows and `~/Library/Application Support` on macOS, which
+/// hold both config and data; `xdg_var` or `~/home_fallback` elsewhere.
+fn app_dir(xdg_var: &str, home_fallback: &str) -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        std::env::var_os(xdg_var)
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(home_fallback)))
+    };
+    base.map(|base| base.join(APP_IDENTIFIER))
+}
+
+fn quarantine_data_dir() -> Result<PathBuf, String> {
+    data_dir().ok_or_else(|| "Pasta de dados indisponível".to_string())
+}
+
+/// Waits while the app, or another run, has it open.
+fn open_quarantine() -> Result<Quarantine, String> {
+    Quarantine::open(&quarantine_data_dir()?)
+}
@@ -0,0 +1,363 @@
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::exclusions::Exclusions;
+use crate::filters::{epoch_millis, now_millis, EntryFilters};
+use crate::i18n::Catalog;
+use crate::rules::CleaningRule;
+use crate::scan::ScanControl;
+use crate::session::FileIdentity;
+use crate::walk::{device_of, DirStats, Walker, DEFAULT_MAX_DEPTH};
+use crate::{EntryKind, TempFile};
+
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// A build output folder, recognised by its name and a marker file next to
+/// it (or inside it, for virtualenvs).
+struct ArtifactKind {
+    category: &'static str,
+    dir_name: &'static str,
+    /// Any of these in the parent folder makes it a project. Empty means the
+    /// folder name alone is enough.
+    markers: &'static [&'static str],
+    /// A file that must exist inside the folder.
+    inner_marker: Option<&'static str>,
+}
+
+const GRADLE_FILES: &[&str] = &[
+    "build.gradle",
+    "build.gradle.kts",
+    "settings.gradle",
+    "settings.gradle.kts",
+];
+
+/// Files that make a folder the root of a Python project, which then owns
+/// every `__pycache__` below it.
+const PYTHON_MARKERS: &[&str] = &[
+    "pyproject.toml",
+    "setup.py",
+    "setup.cfg",
+    "requirements.txt",
+    "Pipfile",
+];
+
+const KINDS: [ArtifactKind; 6] = [
+    ArtifactKind {
+        category: "rust-target",
+        dir_name: "target",
+        markers: &["Cargo.toml"],
+        inner_marker: None,
+    },
+    ArtifactKind {
+        category: "node-modules",
+        dir_name: "node_modules",
+        markers: &["package.json"],
+        inner_marker: None,
+    },
+    ArtifactKind {
+        category: "gradle-build",
+        dir_name: "build",
+        markers: GRADLE_FILES,
+        inner_marker: None,
+    },
+    ArtifactKind {
+        category: "gradle-project-cache",
+        dir_name: ".gradle",
+        markers: GRADLE_FILES,
+        inner_marker: None,
+    },
+    ArtifactKind {
+        category: "python-bytecode",
+        dir_name: "__pycache__",
+        markers: &[],
+        inner_marker: None,
+    },
+    ArtifactKind {
+        category: "python-venv",
+        dir_name: ".venv",
+        markers: &[],
+        inner_marker: Some("pyvenv.cfg"),
+    },
+];
+
+/// Folders never searched for projects.
+const SKIPPED_DIRS: [&str; 3] = [".git", ".hg", ".svn"];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArtifactSort {
+    /// Largest first.
+    #[default]
+    Size,
+    /// Projects untouched the longest first.
+    Staleness,
+}
+
+/// What `find_build_artifacts` looks for.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ArtifactQuery {
+    /// Folders to search, e.g. `~/code`.
+    pub roots: Vec<String>,
+    #[serde(default)]
+    pub filters: EntryFilters,
+    /// Only report projects untouched for at least this many days.
+    #[serde(default)]
+    pub stale_days: Option<u32>,
+    #[serde(default)]
+    pub sort: ArtifactSort,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectInfo {
+    pub path: String,
+    /// Newest modification time of the project's own files, build output
+    /// excluded, in milliseconds since the Unix epoch.
+    pub last_modified: Option<u64>,
+    pub artifact_size: u64,
+}
+
+struct Found {
+    project: PathBuf,
+    kind: &'static ArtifactKind,
+    path: PathBuf,
+    metadata: fs::Metadata,
+    stats: DirStats,
+}
+
+#[derive(Default)]
+struct Visit {
+    newest: Option<u64>,
+    found: Vec<Found>,
+    projects: Vec<(PathBuf, Option<u64>)>,
+}
+
+impl Visit {
+    fn merge(mut self, other: Visit) -> Visit {
+        self.newest = self.newest.max(other.newest);
+        self.found.extend(other.found);
+        self.projects.extend(other.projects);
+        self
+    }
+}
+
+/// Walks user-chosen folders for project build output. Symlinks are never
+/// followed and the search stays on the filesystem of each root.
+pub struct ArtifactFinder<'a> {
+    control: &'a ScanControl,
+    catalog: &'a Catalog,
+    /// Only report projects untouched for at least this many days.
+    stale_days: Option<u32>,
+    exclusions: Option<&'a Exclusions>,
+}
+
+impl<'a> ArtifactFinder<'a> {
+    pub fn new(control: &'a ScanControl, catalog: &'a Catalog) -> Self {
+        ArtifactFinder {
+            control,
+            catalog,
+            stale_days: None,
+            exclusions: None,
+        }
+    }
+
+    /// Skips build output the user excluded.
+    pub fn exclusions(mut self, exclusions: &'a Exclusions) -> Self {
+        self.exclusions = Some(exclusions);
+        self
+    }
+
+    pub fn stale_days(mut self, days: Option<u32>) -> Self {
+        self.stale_days = days;
+        self
+    }
+
+    /// Build output below `root` that passes every `extra` filter layer, and
+    /// the projects it belongs to.
+    pub fn find(&self, root: &Path, extra: &[&EntryFilters]) -> (Vec<TempFile>, Vec<ProjectInfo>) {
+        let metadata = match fs::symlink_metadata(root) {
+            Ok(metadata) if metadata.is_dir() => metadata,
+            _ => return (Vec::new(), Vec::new()),
+        };
+        let python_root = has_any(root, PYTHON_MARKERS).then_some(root);
+        let visit = self.visit(root, 0, python_root, device_of(&metadata));
+
+        let last_modified: HashMap<PathBuf, Option<u64>> = visit.projects.into_iter().collect();
+        let now = now_millis();
+        let stale = |project: &Path| match (self.stale_days, last_modified.get(project)) {
+            (Some(days), Some(Some(newest))) => {
+                now.saturating_sub(*newest) >= u64::from(days) * DAY_MS
+            }
+            _ => true,
+        };
+
+        let mut projects: Vec<ProjectInfo> = Vec::new();
+        let mut files = Vec::new();
+        for found in visit.found {
+            if !stale(&found.project) {
+                continue;
+            }
+            let file = self.entry(&found);
+            if !extra.iter().all(|filters| filters.matches(&file, now))
+                || self
+                    .exclusions
+                    .is_some_and(|e| e.excludes(&found.path, true, &file.category_id))
+            {
+                continue;
+            }
+            let project = found.project.to_string_lossy().to_string();
+            match projects.iter_mut().find(|p| p.path == project) {
+                Some(info) => info.artifact_size += file.size,
+                None => projects.push(ProjectInfo {
+                    last_modified: last_modified.get(&found.project).copied().flatten(),
+                    path: project,
+                    artifact_size: file.size,
+                }),
+            }
+            files.push(file);
+        }
+        (files, projects)
+    }
+
+    fn visit(
+        &self,
+        dir: &Path,
+        depth: usize,
+        python_root: Option<&Path>,
+        device: Option<u64>,
+    ) -> Visit {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(_) => return Visit::default(),
+        };
+
+        let mut visit = Visit::default();
+        let mut subdirs: Vec<PathBuf> = Vec::new();
+        let mut candidates: Vec<(PathBuf, fs::Metadata, &'static ArtifactKind)> = Vec::new();
+
+        for entry in entries.flatten() {
+            if self.control.is_cancelled() {
+                break;
+            }
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+            self.control.record_entry(0);
+            let name = entry.file_name().to_string_lossy().to_string();
+
+            if !metadata.is_dir() || metadata.file_type().is_symlink() {
+                let modified = metadata.modified().ok().and_then(epoch_millis);
+                visit.newest = visit.newest.max(modified);
+                continue;
+            }
+            let path = entry.path();
+            if let Some(kind) = KINDS.iter().find(|kind| kind.dir_name == name) {
+                if self.is_artifact(kind, dir, &path) {
+                    candidates.push((path, metadata, kind));
+                    continue;
+                }
+            }
+            if depth < DEFAULT_MAX_DEPTH
+                && !SKIPPED_DIRS.contains(&name.as_str())
+                && device_of(&metadata) == device
+            {
+                subdirs.push(path);
+            }
+        }
+
+        let found: Vec<Found> = candidates
+            .into_par_iter()
+            .map(|(path, metadata, kind)| Found {
+                project: match (kind.markers.is_empty(), python_root) {
+                    (true, Some(python_root)) => python_root.to_path_buf(),
+                    _ => dir.to_path_buf(),
+                },
+                stats: Walker::new(self.control).one_file_system(true).walk(&path),
+                kind,
+                path,
+                metadata,
+            })
+            .collect();
+        visit.found.extend(found);
+
+        let mut visit = subdirs
+            .par_iter()
+            .map(|subdir| {
+                let python_root = if has_any(subdir, PYTHON_MARKERS) {
+                    Some(subdir.as_path())
+                } else {
+                    python_root
+                };
+                self.visit(subdir, depth + 1, python_root, device)
+            })
+            .reduce(Visit::default, Visit::merge)
+            .merge(visit);
+
+        // Projects are only complete once everything below them is seen.
+        if visit.found.iter().any(|f| f.project == dir) {
+            visit.projects.push((dir.to_path_buf(), visit.newest));
+        }
+        visit
+    }
+
+    fn is_artifact(&self, kind: &ArtifactKind, parent: &Path, path: &Path) -> bool {
+        (kind.markers.is_empty() || has_any(parent, kind.markers))
+            && kind.inner_marker.is_none_or(|marker| path.join(marker).is_file())
+    }
+
+    fn entry(&self, found: &Found) -> TempFile {
+        let modified = found.metadata.modified().ok().and_then(epoch_millis);
+        TempFile {
+            path: found.path.to_string_lossy().to_string(),
+            name: found.kind.dir_name.to_string(),
+            size: found.stats.bytes,
+            kind: EntryKind::Dir,
+            category_id: found.kind.category.to_string(),
+            category: self
+                .catalog
+                .category(found.kind.category)
+                .map(|c| c.name.clone())
+                .unwrap_or_else(|| found.kind.category.to_string()),
+            root_path: found.project.to_string_lossy().to_string(),
+            modified: modified.max(found.stats.last_modified),
+            accessed: found.metadata.accessed().ok().and_then(epoch_millis),
+            created: found.metadata.created().ok().and_then(epoch_millis),
+            identity: Some(FileIdentity::of(&found.metadata)),
+            in_use: Vec::new(),
+        }
+    }
+}
+
+/// The rule a scan session uses to check and delete build output of
+/// `project`: the project folder is the root.
+pub fn project_rule(project: &Path) -> CleaningRule {
+    let path = project.to_string_lossy().to_string();
+    CleaningRule::new(format!("artifacts:{}", path), path, "build-artifacts")
+}
+
+pub fn sort(files: &mut [TempFile], projects: &mut [ProjectInfo], order: ArtifactSort) {
+    match order {
+        ArtifactSort::Size => {
+            files.sort_by_key(|file| Reverse(file.size));
+            projects.sort_by_key(|p| Reverse(p.artifact_size));
+        }
+        ArtifactSort::Staleness => {
+            let modified: HashMap<&str, Option<u64>> = projects
+                .iter()
+                .map(|p| (p.path.as_str(), p.last_modified))
+                .collect();
+            // Unknown times sort last.
+            let key = |root: &str| modified.get(root).copied().flatten().unwrap_or(u64::MAX);
+            files.sort_by_key(|file| key(&file.root_path));
+            projects.sort_by_key(|p| p.last_modified.unwrap_or(u64::MAX));
+        }
+    }
+}
+
+fn has_any(dir: &Path, names: &[&str]) -> bool {
+    names.iter().any(|name| dir.join(name).is_file())
+}
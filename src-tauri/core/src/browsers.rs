@@ -0,0 +1,67 @@
+use crate::roots::RootResolver;
+use crate::rules::{CleaningRule, Platform, RuleFamily};
+
+mod chromium;
+mod firefox;
+
+/// Cleaning rules for the browser profiles found where `resolver` points,
+/// this machine by default. Only disposable folders inside each profile
+/// become roots, never the profile itself.
+pub fn discover(resolver: &RootResolver) -> Vec<CleaningRule> {
+    let mut rules = firefox::discover(resolver);
+    rules.extend(chromium::discover(resolver));
+    rules
+}
+
+/// A rule for one disposable folder of a browser profile.
+fn profile_rule(
+    id: String,
+    path: String,
+    category: &str,
+    platform: Platform,
+    profile: &str,
+) -> CleaningRule {
+    let mut rule = CleaningRule::new(id, path, category);
+    rule.family = RuleFamily::Browser;
+    rule.platform = Some(platform);
+    rule.profile = Some(profile.to_string());
+    rule
+}
+
+/// `Code Cache` becomes `code-cache`, for use in rule ids.
+fn slug(text: &str) -> String {
+    text.split(|c: char| !c.is_alphanumeric() && c != '.')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+        .to_lowercase()
+}
+
+/// Path templates are expanded later, so a discovered path must not contain
+/// anything [`crate::rules::expand_path`] would treat as a variable, nor
+/// climb out of its base directory.
+fn is_plain_path(path: &str) -> bool {
+    !path.contains(['%', '$'])
+        && !path.starts_with('~')
+        && !path
+            .split(['/', '\\'])
+            .any(|part| part == ".." || part == ".")
+}
+
+/// Joins a relative profile path onto a base template with the separator of
+/// `platform`. An empty `relative` leaves `base` as is.
+fn join_template(base: &str, relative: &str, platform: Platform) -> String {
+    let separator = match platform {
+        Platform::Windows => "\\",
+        _ => "/",
+    };
+    let relative = relative
+        .split(['/', '\\'])
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(separator);
+    if relative.is_empty() {
+        return base.to_string();
+    }
+    format!("{}{}{}", base, separator, relative)
+}
@@ -0,0 +1,210 @@
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+use super::{is_plain_path, join_template, profile_rule, slug};
+use crate::roots::RootResolver;
+use crate::rules::{CleaningRule, Platform};
+
+/// Where one Chromium-based browser keeps its profiles (`user_data`, which
+/// holds `Local State`) and their disk caches (`cache_data`; the same folder
+/// on Windows, under `~/.cache` on Linux).
+struct Browser {
+    id: &'static str,
+    category: &'static str,
+    platform: Platform,
+    user_data: &'static str,
+    cache_data: &'static str,
+    /// Opera keeps a single profile directly in the user data folder.
+    single_profile: bool,
+}
+
+const fn windows(id: &'static str, category: &'static str, user_data: &'static str) -> Browser {
+    Browser {
+        id,
+        category,
+        platform: Platform::Windows,
+        user_data,
+        cache_data: user_data,
+        single_profile: false,
+    }
+}
+
+const fn linux(
+    id: &'static str,
+    category: &'static str,
+    user_data: &'static str,
+    cache_data: &'static str,
+) -> Browser {
+    Browser {
+        id,
+        category,
+        platform: Platform::Linux,
+        user_data,
+        cache_data,
+        single_profile: false,
+    }
+}
+
+const BROWSERS: [Browser; 12] = [
+    windows("chrome", "chrome-cache", r"%LOCALAPPDATA%\Google\Chrome\User Data"),
+    windows("edge", "edge-cache", r"%LOCALAPPDATA%\Microsoft\Edge\User Data"),
+    windows(
+        "brave",
+        "brave-cache",
+        r"%LOCALAPPDATA%\BraveSoftware\Brave-Browser\User Data",
+    ),
+    windows("vivaldi", "vivaldi-cache", r"%LOCALAPPDATA%\Vivaldi\User Data"),
+    windows("chromium", "chromium-cache", r"%LOCALAPPDATA%\Chromium\User Data"),
+    Browser {
+        id: "opera",
+        category: "opera-cache",
+        platform: Platform::Windows,
+        user_data: r"%APPDATA%\Opera Software\Opera Stable",
+        cache_data: r"%LOCALAPPDATA%\Opera Software\Opera Stable",
+        single_profile: true,
+    },
+    linux(
+        "chrome",
+        "chrome-cache",
+        "${XDG_CONFIG_HOME:-~/.config}/google-chrome",
+        "${XDG_CACHE_HOME:-~/.cache}/google-chrome",
+    ),
+    linux(
+        "edge",
+        "edge-cache",
+        "${XDG_CONFIG_HOME:-~/.config}/microsoft-edge",
+        "${XDG_CACHE_HOME:-~/.cache}/microsoft-edge",
+    ),
+    linux(
+        "brave",
+        "brave-cache",
+        "${XDG_CONFIG_HOME:-~/.config}/BraveSoftware/Brave-Browser",
+        "${XDG_CACHE_HOME:-~/.cache}/BraveSoftware/Brave-Browser",
+    ),
+    linux(
+        "vivaldi",
+        "vivaldi-cache",
+        "${XDG_CONFIG_HOME:-~/.config}/vivaldi",
+        "${XDG_CACHE_HOME:-~/.cache}/vivaldi",
+    ),
+    linux(
+        "chromium",
+        "chromium-cache",
+        "${XDG_CONFIG_HOME:-~/.config}/chromium",
+        "${XDG_CACHE_HOME:-~/.cache}/chromium",
+    ),
+    Browser {
+        id: "opera",
+        category: "opera-cache",
+        platform: Platform::Linux,
+        user_data: "${XDG_CONFIG_HOME:-~/.config}/opera",
+        cache_data: "${XDG_CACHE_HOME:-~/.cache}/opera",
+        single_profile: true,
+    },
+];
+
+/// Disposable folders in the cache half of a profile.
+const CACHE_FOLDERS: [&str; 2] = ["Cache", "Code Cache"];
+
+/// Disposable folders in the profile folder itself.
+const PROFILE_FOLDERS: [&str; 1] = ["GPUCache"];
+
+/// Folder and display name of each profile.
+struct Profile {
+    dir: String,
+    name: String,
+}
+
+pub fn discover(resolver: &RootResolver) -> Vec<CleaningRule> {
+    let current = resolver.target_platform();
+    BROWSERS
+        .iter()
+        .filter(|browser| Some(browser.platform) == current)
+        .flat_map(|browser| rules_for(browser, resolver))
+        .collect()
+}
+
+fn rules_for(browser: &Browser, resolver: &RootResolver) -> Vec<CleaningRule> {
+    let user_data = match resolver.resolve(browser.user_data) {
+        Some(dir) if dir.is_dir() => dir,
+        _ => return Vec::new(),
+    };
+
+    let mut rules = Vec::new();
+    for profile in profiles(browser, &user_data) {
+        if !is_plain_path(&profile.dir) {
+            continue;
+        }
+        let profile_dir = join_template(browser.user_data, &profile.dir, browser.platform);
+        let cache_dir = join_template(browser.cache_data, &profile.dir, browser.platform);
+
+        let targets = CACHE_FOLDERS
+            .iter()
+            .map(|folder| (&cache_dir, folder))
+            .chain(PROFILE_FOLDERS.iter().map(|folder| (&profile_dir, folder)));
+        for (dir, folder) in targets {
+            let id = if browser.single_profile {
+                format!("{}-{}", browser.id, slug(folder))
+            } else {
+                format!("{}-{}-{}", browser.id, slug(&profile.dir), slug(folder))
+            };
+            rules.push(profile_rule(
+                id,
+                join_template(dir, folder, browser.platform),
+                browser.category,
+                browser.platform,
+                &profile.name,
+            ));
+        }
+    }
+    rules
+}
+
+/// Profiles listed under `profile.info_cache` in `Local State`. Falls back to
+/// `Default` when the file is missing or unreadable.
+fn profiles(browser: &Browser, user_data: &Path) -> Vec<Profile> {
+    let local_state = fs::read_to_string(user_data.join("Local State"))
+        .ok()
+        .and_then(|text| serde_json::from_str::<Value>(&text).ok());
+
+    if browser.single_profile {
+        let name = local_state
+            .as_ref()
+            .and_then(|state| state.pointer("/profile/info_cache/Default/name"))
+            .and_then(Value::as_str)
+            .unwrap_or("Default");
+        return vec![Profile {
+            dir: String::new(),
+            name: name.to_string(),
+        }];
+    }
+
+    let listed: Vec<Profile> = local_state
+        .as_ref()
+        .and_then(|state| state.pointer("/profile/info_cache"))
+        .and_then(Value::as_object)
+        .map(|cache| {
+            cache
+                .iter()
+                .map(|(dir, info)| Profile {
+                    dir: dir.clone(),
+                    name: info
+                        .get("name")
+                        .and_then(Value::as_str)
+                        .filter(|name| !name.is_empty())
+                        .unwrap_or(dir)
+                        .to_string(),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    if listed.is_empty() && user_data.join("Default").is_dir() {
+        return vec![Profile {
+            dir: "Default".to_string(),
+            name: "Default".to_string(),
+        }];
+    }
+    listed
+}
@@ -0,0 +1,216 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use super::{is_plain_path, join_template, profile_rule, slug};
+use crate::roots::RootResolver;
+use crate::rules::{CleaningRule, Platform};
+
+const CATEGORY: &str = "firefox-cache";
+
+/// Where one Firefox build keeps `profiles.ini` and where the local (cache)
+/// half of its relative profiles lives.
+struct Layout {
+    platform: Platform,
+    config_dir: &'static str,
+    local_dir: &'static str,
+}
+
+const LAYOUTS: [Layout; 3] = [
+    Layout {
+        platform: Platform::Windows,
+        config_dir: r"%APPDATA%\Mozilla\Firefox",
+        local_dir: r"%LOCALAPPDATA%\Mozilla\Firefox",
+    },
+    Layout {
+        platform: Platform::Linux,
+        config_dir: "~/.mozilla/firefox",
+        local_dir: "${XDG_CACHE_HOME:-~/.cache}/mozilla/firefox",
+    },
+    // Newer builds follow the XDG base directories.
+    Layout {
+        platform: Platform::Linux,
+        config_dir: "${XDG_CONFIG_HOME:-~/.config}/mozilla/firefox",
+        local_dir: "${XDG_CACHE_HOME:-~/.cache}/mozilla/firefox",
+    },
+];
+
+/// Disposable folders in the local half of a profile. Firefox rebuilds them
+/// on demand.
+const LOCAL_CACHES: [&str; 5] = [
+    "cache2",
+    "startupCache",
+    "thumbnails",
+    "jumpListCache",
+    "OfflineCache",
+];
+
+/// Disposable folders in the profile folder itself.
+const PROFILE_CACHES: [&str; 1] = ["shader-cache"];
+
+#[derive(Debug)]
+struct Profile {
+    name: String,
+    path: String,
+    is_relative: bool,
+}
+
+pub fn discover(resolver: &RootResolver) -> Vec<CleaningRule> {
+    let current = resolver.target_platform();
+    let mut keys = HashMap::new();
+    let mut paths = HashSet::new();
+    // Both Linux layouts keep their cache in the same place, so a profile
+    // listed in both would be scanned twice.
+    LAYOUTS
+        .iter()
+        .filter(|layout| Some(layout.platform) == current)
+        .flat_map(|layout| rules_for(layout, resolver, &mut keys))
+        .filter(|rule| paths.insert(rule.path.clone()))
+        .collect()
+}
+
+/// `keys` maps the rule id key of each profile found so far to its folder.
+fn rules_for(
+    layout: &Layout,
+    resolver: &RootResolver,
+    keys: &mut HashMap<String, String>,
+) -> Vec<CleaningRule> {
+    let config_dir = match resolver.resolve(layout.config_dir) {
+        Some(dir) => dir,
+        None => return Vec::new(),
+    };
+
+    let mut rules = Vec::new();
+    for profile in profiles(&config_dir) {
+        if !is_plain_path(&profile.path) {
+            continue;
+        }
+        // Relative profiles are split between the config and local dirs;
+        // absolute ones keep their cache in the profile folder.
+        let (profile_dir, local_dir) = if profile.is_relative {
+            (
+                join_template(layout.config_dir, &profile.path, layout.platform),
+                join_template(layout.local_dir, &profile.path, layout.platform),
+            )
+        } else {
+            (profile.path.clone(), profile.path.clone())
+        };
+        let key = unique_key(keys, &profile_dir, &profile.name);
+
+        let targets = LOCAL_CACHES
+            .iter()
+            .map(|folder| (&local_dir, folder))
+            .chain(PROFILE_CACHES.iter().map(|folder| (&profile_dir, folder)));
+        for (dir, folder) in targets {
+            rules.push(profile_rule(
+                format!("firefox-{}-{}", key, slug(folder)),
+                join_template(dir, folder, layout.platform),
+                CATEGORY,
+                layout.platform,
+                &profile.name,
+            ));
+        }
+    }
+    rules
+}
+
+/// The profile folder's name, e.g. `ab12.default-release`, followed by a
+/// hash of its whole path when another profile already goes by that name,
+/// so the rule ids of both stay apart.
+fn unique_key(keys: &mut HashMap<String, String>, profile_dir: &str, name: &str) -> String {
+    let mut key = Path::new(profile_dir)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| name.to_string());
+    if keys.get(&key).is_some_and(|dir| dir != profile_dir) {
+        let hash = blake3::hash(profile_dir.as_bytes()).to_hex();
+        key = format!("{}-{}", key, &hash[..8]);
+    }
+    keys.insert(key.clone(), profile_dir.to_string());
+    key
+}
+
+/// Profiles listed in `profiles.ini`, plus install defaults from
+/// `installs.ini` that it doesn't mention.
+fn profiles(config_dir: &Path) -> Vec<Profile> {
+    let mut found: Vec<Profile> = Vec::new();
+    let mut add = |profile: Profile| {
+        if !found.iter().any(|p| same_path(&p.path, &profile.path)) {
+            found.push(profile);
+        }
+    };
+
+    let mut install_defaults = Vec::new();
+    if let Ok(text) = fs::read_to_string(config_dir.join("profiles.ini")) {
+        for (section, keys) in parse_ini(&text) {
+            if section.starts_with("Profile") {
+                if let Some(path) = value(&keys, "Path") {
+                    add(Profile {
+                        name: value(&keys, "Name")
+                            .map(str::to_string)
+                            .unwrap_or_else(|| dir_name(path)),
+                        path: path.to_string(),
+                        is_relative: value(&keys, "IsRelative") != Some("0"),
+                    });
+                }
+            } else if section.starts_with("Install") {
+                install_defaults.extend(value(&keys, "Default").map(str::to_string));
+            }
+        }
+    }
+    if let Ok(text) = fs::read_to_string(config_dir.join("installs.ini")) {
+        for (_, keys) in parse_ini(&text) {
+            install_defaults.extend(value(&keys, "Default").map(str::to_string));
+        }
+    }
+
+    // Install defaults are always relative to the config dir.
+    for path in install_defaults {
+        add(Profile {
+            name: dir_name(&path),
+            path,
+            is_relative: true,
+        });
+    }
+    found
+}
+
+fn same_path(a: &str, b: &str) -> bool {
+    a.replace('\\', "/").eq_ignore_ascii_case(&b.replace('\\', "/"))
+}
+
+/// `abcd1234.default-release` for `Profiles/abcd1234.default-release`.
+fn dir_name(path: &str) -> String {
+    path.rsplit(['/', '\\'])
+        .find(|part| !part.is_empty())
+        .unwrap_or(path)
+        .to_string()
+}
+
+type Section<'a> = (&'a str, Vec<(&'a str, &'a str)>);
+
+/// Sections of an INI file, in order, with their `key=value` pairs.
+fn parse_ini(text: &str) -> Vec<Section<'_>> {
+    let mut sections: Vec<Section<'_>> = Vec::new();
+    for line in text.trim_start_matches('\u{feff}').lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with([';', '#']) {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            sections.push((name.trim(), Vec::new()));
+        } else if let (Some((_, keys)), Some((key, value))) =
+            (sections.last_mut(), line.split_once('='))
+        {
+            keys.push((key.trim(), value.trim()));
+        }
+    }
+    sections
+}
+
+fn value<'a>(keys: &[(&str, &'a str)], key: &str) -> Option<&'a str> {
+    keys.iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(key))
+        .map(|(_, v)| *v)
+        .filter(|v| !v.is_empty())
+}
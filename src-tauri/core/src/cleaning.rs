@@ -0,0 +1,188 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::filters::now_millis;
+use crate::progress::ProgressObserver;
+use crate::{DeleteProgress, DeleteReport};
+
+/// Finished jobs kept around for [`DeleteJobs::get`].
+const KEPT_JOBS: usize = 8;
+
+type Reporter = Box<dyn Fn(DeleteProgress) + Send + Sync>;
+
+/// Shared state of a cleaning run: cancellation flag, latest progress and
+/// the progress reporter. [`crate::delete_entries`] checks for cancellation
+/// between paths, so a path it started on is always seen through.
+#[derive(Default)]
+pub struct DeleteControl {
+    cancelled: AtomicBool,
+    latest: Mutex<Option<DeleteProgress>>,
+    reporter: Option<Reporter>,
+}
+
+impl DeleteControl {
+    pub fn new<F>(reporter: F) -> Self
+    where
+        F: Fn(DeleteProgress) + Send + Sync + 'static,
+    {
+        DeleteControl {
+            reporter: Some(Box::new(reporter)),
+            ..Default::default()
+        }
+    }
+
+    /// Reports progress to `observer`.
+    pub fn observed(observer: Arc<dyn ProgressObserver>) -> Self {
+        DeleteControl::new(move |progress| observer.delete_progress(progress))
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// The progress last reported, if any.
+    pub fn latest(&self) -> Option<DeleteProgress> {
+        self.latest.lock().ok().and_then(|latest| latest.clone())
+    }
+
+    pub fn report(&self, progress: DeleteProgress) {
+        if let Ok(mut latest) = self.latest.lock() {
+            *latest = Some(progress.clone());
+        }
+        if let Some(reporter) = &self.reporter {
+            reporter(progress);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobState {
+    Running,
+    Finished,
+    /// Stopped by a cancel; the paths handled until then are in the report.
+    Cancelled,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeleteJobStatus {
+    pub job_id: String,
+    pub state: JobState,
+    /// Milliseconds since the Unix epoch.
+    pub started_at: u64,
+    /// `None` until the first path is handled.
+    pub progress: Option<DeleteProgress>,
+    /// Set once the job is no longer running, unless it failed.
+    pub report: Option<DeleteReport>,
+    pub error: Option<String>,
+}
+
+/// A cleaning run in the background, looked up by id so a reloaded window
+/// can find it again.
+pub struct DeleteJob {
+    id: String,
+    started_at: u64,
+    control: DeleteControl,
+    result: Mutex<Option<Result<DeleteReport, String>>>,
+}
+
+impl DeleteJob {
+    pub fn new(control: DeleteControl) -> Arc<Self> {
+        Arc::new(DeleteJob {
+            id: uuid::Uuid::new_v4().to_string(),
+            started_at: now_millis(),
+            control,
+            result: Mutex::new(None),
+        })
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn control(&self) -> &DeleteControl {
+        &self.control
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.result.lock().map(|result| result.is_none()).unwrap_or(false)
+    }
+
+    pub fn finish(&self, result: Result<DeleteReport, String>) {
+        if let Ok(mut slot) = self.result.lock() {
+            *slot = Some(result);
+        }
+    }
+
+    pub fn status(&self) -> DeleteJobStatus {
+        let result = self.result.lock().ok().and_then(|result| result.clone());
+        let (state, report, error) = match result {
+            None => (JobState::Running, None, None),
+            Some(Err(error)) => (JobState::Failed, None, Some(error)),
+            Some(Ok(report)) if report.cancelled => (JobState::Cancelled, Some(report), None),
+            Some(Ok(report)) => (JobState::Finished, Some(report), None),
+        };
+        DeleteJobStatus {
+            job_id: self.id.clone(),
+            state,
+            started_at: self.started_at,
+            progress: self.control.latest(),
+            report,
+            error,
+        }
+    }
+}
+
+/// Cleaning runs of this app session, oldest first: the running ones and
+/// the last [`KEPT_JOBS`] finished.
+#[derive(Default)]
+pub struct DeleteJobs {
+    jobs: Mutex<Vec<Arc<DeleteJob>>>,
+}
+
+impl DeleteJobs {
+    pub fn start(&self, job: Arc<DeleteJob>) {
+        if let Ok(mut jobs) = self.jobs.lock() {
+            let mut finished = jobs.iter().filter(|job| !job.is_running()).count();
+            jobs.retain(|job| {
+                let drop = finished > KEPT_JOBS && !job.is_running();
+                if drop {
+                    finished -= 1;
+                }
+                !drop
+            });
+            jobs.push(job);
+        }
+    }
+
+    /// The job with `id`, or the most recent one when `id` is `None`.
+    pub fn get(&self, id: Option<&str>) -> Option<Arc<DeleteJob>> {
+        let jobs = self.jobs.lock().ok()?;
+        match id {
+            Some(id) => jobs.iter().find(|job| job.id == id).cloned(),
+            None => jobs.last().cloned(),
+        }
+    }
+
+    /// Cancels the job with `id`, or every running job when `id` is `None`.
+    /// Returns false when there was nothing to cancel.
+    pub fn cancel(&self, id: Option<&str>) -> bool {
+        let Ok(jobs) = self.jobs.lock() else {
+            return false;
+        };
+        let mut cancelled = false;
+        for job in jobs.iter() {
+            if job.is_running() && id.is_none_or(|id| job.id == id) {
+                job.control.cancel();
+                cancelled = true;
+            }
+        }
+        cancelled
+    }
+}
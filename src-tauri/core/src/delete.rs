@@ -0,0 +1,351 @@
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::{Component, Path};
+
+use crate::filesystem::{FileKind, FileSystem, Metadata};
+
+/// Failures listed per entry, predicted or actual; the rest are counted.
+const MAX_LISTED_FAILURES: usize = 50;
+
+/// Why removing something failed, or is expected to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureKind {
+    PermissionDenied,
+    /// The read-only attribute on Windows, or a read-only mount.
+    ReadOnly,
+    /// A process has the file open, which Windows refuses to remove.
+    InUse,
+    NotFound,
+    /// The path is protected by one of the user's exclusions.
+    Excluded,
+    /// Another file system is mounted here, and left alone.
+    MountPoint,
+    Other,
+}
+
+impl FailureKind {
+    pub fn of(error: &io::Error) -> FailureKind {
+        /// ERROR_SHARING_VIOLATION
+        const SHARING_VIOLATION: i32 = 32;
+        match error.kind() {
+            io::ErrorKind::PermissionDenied => FailureKind::PermissionDenied,
+            io::ErrorKind::ReadOnlyFilesystem => FailureKind::ReadOnly,
+            io::ErrorKind::NotFound => FailureKind::NotFound,
+            io::ErrorKind::ResourceBusy => FailureKind::InUse,
+            _ if cfg!(windows) && error.raw_os_error() == Some(SHARING_VIOLATION) => {
+                FailureKind::InUse
+            }
+            _ => FailureKind::Other,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemovalFailure {
+    pub path: String,
+    pub kind: FailureKind,
+}
+
+/// What [`remove_path`] got done. Entries it couldn't remove are skipped,
+/// and so are the directories holding them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Removed {
+    pub files: u64,
+    pub dirs: u64,
+    pub links: u64,
+    /// Size of the files removed.
+    pub bytes: u64,
+    /// Entries left behind for an error of their own; only the first
+    /// [`MAX_LISTED_FAILURES`] are listed in `failures`.
+    pub failed: u64,
+    pub failures: Vec<RemovalFailure>,
+}
+
+impl Removed {
+    pub fn is_empty(&self) -> bool {
+        self.files + self.dirs + self.links == 0
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.failed == 0
+    }
+
+    pub fn first_failure(&self) -> Option<FailureKind> {
+        self.failures.first().map(|failure| failure.kind)
+    }
+
+    pub fn fail(&mut self, path: &Path, kind: FailureKind) {
+        self.failed += 1;
+        if self.failures.len() < MAX_LISTED_FAILURES {
+            self.failures.push(RemovalFailure {
+                path: path.to_string_lossy().to_string(),
+                kind,
+            });
+        }
+    }
+
+    /// Removes what is below `path`, then `path`. Returns whether it is gone.
+    fn visit(
+        &mut self,
+        fs: &dyn FileSystem,
+        path: &Path,
+        metadata: &Metadata,
+        device: Option<u64>,
+    ) -> bool {
+        if metadata.is_dir() {
+            // A directory without write permission can't be emptied.
+            if metadata.readonly {
+                let _ = fs.clear_readonly(path);
+            }
+            let entries = match fs.read_dir(path) {
+                Ok(entries) => entries,
+                Err(error) => {
+                    self.fail(path, FailureKind::of(&error));
+                    return false;
+                }
+            };
+            let mut emptied = true;
+            for entry in entries {
+                // Mount points are left alone, and so is the directory
+                // holding them.
+                let other_device =
+                    entry.metadata.is_dir() && device.is_some() && entry.metadata.device != device;
+                if other_device {
+                    self.fail(&entry.path, FailureKind::MountPoint);
+                    emptied = false;
+                } else if !self.visit(fs, &entry.path, &entry.metadata, device) {
+                    emptied = false;
+                }
+            }
+            if !emptied {
+                return false;
+            }
+        }
+
+        match remove_entry(fs, path, metadata) {
+            Ok(()) => {
+                match metadata.kind {
+                    FileKind::Link => self.links += 1,
+                    FileKind::Dir => self.dirs += 1,
+                    _ => {
+                        self.files += 1;
+                        self.bytes += metadata.len;
+                    }
+                }
+                true
+            }
+            Err(error) => {
+                self.fail(path, FailureKind::of(&error));
+                false
+            }
+        }
+    }
+}
+
+/// What removing one entry would do, as walked by [`plan_removal`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RemovalPlan {
+    /// Files, directories and links expected to be removed, and the bytes
+    /// that frees.
+    pub files: u64,
+    pub dirs: u64,
+    pub links: u64,
+    pub bytes: u64,
+    /// Everything expected to stay behind; only the first
+    /// [`MAX_LISTED_FAILURES`] are listed in `failures`.
+    pub failed: u64,
+    pub failures: Vec<RemovalFailure>,
+}
+
+impl RemovalPlan {
+    pub fn fail(&mut self, path: &Path, kind: FailureKind) {
+        self.failed += 1;
+        if self.failures.len() < MAX_LISTED_FAILURES {
+            self.failures.push(RemovalFailure {
+                path: path.to_string_lossy().to_string(),
+                kind,
+            });
+        }
+    }
+
+    /// Returns whether `path` is expected to be gone afterwards.
+    /// `parent_cleared` tells that [`remove_path`] will make the parent
+    /// writable first.
+    fn visit(
+        &mut self,
+        fs: &dyn FileSystem,
+        path: &Path,
+        metadata: &Metadata,
+        device: Option<u64>,
+        parent_cleared: bool,
+    ) -> bool {
+        let mut removable = match predict_removal(fs, path, metadata, parent_cleared) {
+            Ok(()) => true,
+            Err(kind) => {
+                self.fail(path, kind);
+                false
+            }
+        };
+        match metadata.kind {
+            FileKind::Link => {
+                if removable {
+                    self.links += 1;
+                }
+            }
+            FileKind::Dir => {
+                let cleared = metadata.readonly && metadata.is_owned_by_user();
+                match fs.read_dir(path) {
+                    Ok(entries) => {
+                        for entry in entries {
+                            // Mount points are left alone, and so is the
+                            // directory holding them.
+                            let other_device = entry.metadata.kind == FileKind::Dir
+                                && device.is_some()
+                                && entry.metadata.device != device;
+                            if other_device {
+                                self.fail(&entry.path, FailureKind::MountPoint);
+                                removable = false;
+                            } else if !self.visit(
+                                fs,
+                                &entry.path,
+                                &entry.metadata,
+                                device,
+                                cleared,
+                            ) {
+                                removable = false;
+                            }
+                        }
+                    }
+                    Err(error) => {
+                        if removable {
+                            self.fail(path, FailureKind::of(&error));
+                        }
+                        removable = false;
+                    }
+                }
+                if removable {
+                    self.dirs += 1;
+                }
+            }
+            _ => {
+                if removable {
+                    self.files += 1;
+                    self.bytes += metadata.len;
+                }
+            }
+        }
+        removable
+    }
+}
+
+fn predict_removal(
+    fs: &dyn FileSystem,
+    path: &Path,
+    metadata: &Metadata,
+    parent_cleared: bool,
+) -> Result<(), FailureKind> {
+    // Like `remove_entry`, which clears the attribute and tries again,
+    // except on links.
+    if cfg!(windows) && metadata.readonly {
+        let clearable =
+            metadata.kind != FileKind::Link && fs.check_clear_readonly(path).is_ok();
+        if !clearable {
+            return Err(FailureKind::ReadOnly);
+        }
+    }
+    match fs.check_removable(path) {
+        Err(error) if parent_cleared && error.kind() == io::ErrorKind::PermissionDenied => Ok(()),
+        result => result.map_err(|e| FailureKind::of(&e)),
+    }
+}
+
+/// Walks everything [`remove_path`] would remove, including the contents of
+/// directories, and predicts what would fail. Nothing is touched.
+pub fn plan_removal(fs: &dyn FileSystem, path: &Path, one_file_system: bool) -> RemovalPlan {
+    let mut plan = RemovalPlan::default();
+    match fs.symlink_metadata(path) {
+        Ok(metadata) => {
+            let device = metadata.device.filter(|_| one_file_system);
+            plan.visit(fs, path, &metadata, device, false);
+        }
+        Err(error) => plan.fail(path, FailureKind::of(&error)),
+    }
+    plan
+}
+
+/// Checks that `path` lies strictly inside `root`, also after resolving any
+/// symlink in its parent chain. The last component is deliberately left
+/// unresolved: if it is a link, the link itself is what gets removed.
+pub fn ensure_within_root(fs: &dyn FileSystem, path: &Path, root: &Path) -> Result<(), String> {
+    let outside = || format!("{} está fora de {}", path.display(), root.display());
+
+    if path
+        .components()
+        .any(|c| matches!(c, Component::ParentDir | Component::CurDir))
+        || !path.starts_with(root)
+        || path == root
+    {
+        return Err(outside());
+    }
+
+    let parent = path.parent().ok_or_else(outside)?;
+    let real_root = fs
+        .canonicalize(root)
+        .map_err(|e| format!("{}: {}", root.display(), e))?;
+    let real_parent = fs
+        .canonicalize(parent)
+        .map_err(|e| format!("{}: {}", parent.display(), e))?;
+
+    if real_parent.starts_with(&real_root) {
+        Ok(())
+    } else {
+        Err(outside())
+    }
+}
+
+/// Removes `path` without following symlinks: links are unlinked, never
+/// their targets. With `one_file_system`, directories that live on another
+/// device than `path` (mount points) are left untouched. Removal goes
+/// bottom-up and keeps going past entries that can't be removed.
+pub fn remove_path(fs: &dyn FileSystem, path: &Path, one_file_system: bool) -> Removed {
+    let mut removed = Removed::default();
+    match fs.symlink_metadata(path) {
+        Ok(metadata) => {
+            let device = metadata.device.filter(|_| one_file_system);
+            removed.visit(fs, path, &metadata, device);
+        }
+        Err(error) => removed.fail(path, FailureKind::of(&error)),
+    }
+    removed
+}
+
+/// Removes a single entry; a read-only attribute in the way is cleared
+/// when the user may, and the removal tried again.
+fn remove_entry(fs: &dyn FileSystem, path: &Path, metadata: &Metadata) -> io::Result<()> {
+    let remove = || match metadata.kind {
+        FileKind::Link => remove_link(fs, path),
+        FileKind::Dir => fs.remove_dir(path),
+        _ => fs.remove_file(path),
+    };
+    match remove() {
+        Err(error)
+            if error.kind() == io::ErrorKind::PermissionDenied
+                && cfg!(windows)
+                && metadata.readonly
+                && metadata.kind != FileKind::Link =>
+        {
+            fs.clear_readonly(path).map_err(|_| error)?;
+            remove()
+        }
+        result => result,
+    }
+}
+
+/// Directory symlinks and junctions on Windows need `remove_dir`.
+fn remove_link(fs: &dyn FileSystem, path: &Path) -> io::Result<()> {
+    match fs.remove_file(path) {
+        Err(_) if cfg!(windows) => fs.remove_dir(path),
+        result => result,
+    }
+}
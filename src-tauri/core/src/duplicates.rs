@@ -0,0 +1,503 @@
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::filters::{epoch_millis, now_millis, EntryFilters};
+use crate::i18n::Catalog;
+use crate::progress::ProgressObserver;
+use crate::scan::{ScanControl, Throttle};
+use crate::session::FileIdentity;
+use crate::walk::{device_of, inode_of, DEFAULT_MAX_DEPTH};
+
+/// Bytes read from each end of a file for the partial hash.
+const EDGE_BLOCK: u64 = 4096;
+
+const READ_BUFFER: usize = 64 * 1024;
+
+/// What `find_duplicates` looks at.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DuplicateQuery {
+    pub roots: Vec<String>,
+    /// Size, age and name limits on the files compared.
+    #[serde(default)]
+    pub filters: EntryFilters,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateStage {
+    /// Listing files below the roots.
+    Collecting,
+    /// Hashing the first and last block of files of equal size.
+    PartialHash,
+    /// Hashing the whole content of files that still match.
+    FullHash,
+}
+
+/// Emitted as `duplicate-progress`.
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateProgress {
+    pub stage: DuplicateStage,
+    pub files_done: u64,
+    pub files_total: u64,
+    pub bytes_hashed: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateFile {
+    pub path: String,
+    pub modified: Option<u64>,
+}
+
+/// Files with identical content.
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateSet {
+    /// BLAKE3 of the content, hex encoded.
+    pub hash: String,
+    /// Size of each file.
+    pub size: u64,
+    pub files: Vec<DuplicateFile>,
+    /// Bytes that keeping a single copy would free.
+    pub wasted: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DuplicateScanResult {
+    /// Pass this back to `resolve_duplicates`.
+    pub session_id: String,
+    /// Largest waste first.
+    pub sets: Vec<DuplicateSet>,
+    pub wasted_size: u64,
+    pub cancelled: bool,
+}
+
+#[derive(Debug, Clone)]
+struct Candidate {
+    path: PathBuf,
+    size: u64,
+    modified: Option<u64>,
+    identity: FileIdentity,
+    inode: Option<(u64, u64)>,
+}
+
+/// Finds files with the same content: files are grouped by size, then by a
+/// hash of their first and last block, then by a hash of the whole file, so
+/// only real candidates are read in full. Empty files, symlinks and extra
+/// hard links to an inode already seen are ignored.
+pub struct DuplicateFinder<'a> {
+    control: &'a ScanControl,
+    filters: &'a EntryFilters,
+    observer: &'a dyn ProgressObserver,
+    stage: std::sync::Mutex<DuplicateStage>,
+    files_done: AtomicU64,
+    files_total: AtomicU64,
+    bytes_hashed: AtomicU64,
+    throttle: Throttle,
+}
+
+impl<'a> DuplicateFinder<'a> {
+    pub fn new(
+        control: &'a ScanControl,
+        filters: &'a EntryFilters,
+        observer: &'a dyn ProgressObserver,
+    ) -> Self {
+        DuplicateFinder {
+            control,
+            filters,
+            observer,
+            stage: std::sync::Mutex::new(DuplicateStage::Collecting),
+            files_done: AtomicU64::new(0),
+            files_total: AtomicU64::new(0),
+            bytes_hashed: AtomicU64::new(0),
+            throttle: Throttle::default(),
+        }
+    }
+
+    pub fn find(&self, roots: &[PathBuf]) -> DuplicateSession {
+        self.begin(DuplicateStage::Collecting, 0);
+        let mut files: Vec<Candidate> = roots
+            .iter()
+            .flat_map(|root| self.collect_root(root))
+            .collect();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        files.dedup_by(|a, b| a.path == b.path);
+        let mut seen_inodes = HashSet::new();
+        files.retain(|file| file.inode.is_none_or(|inode| seen_inodes.insert(inode)));
+
+        let by_size = group(files.into_iter().map(|file| (file.size, file)).collect());
+
+        let candidates: Vec<Candidate> = by_size.into_iter().flat_map(|(_, set)| set).collect();
+        self.begin(DuplicateStage::PartialHash, candidates.len());
+        let keyed = candidates
+            .into_par_iter()
+            .filter_map(|file| {
+                let hash = self.step(partial_hash(&file.path, file.size).ok())?;
+                Some(((file.size, hash), file))
+            })
+            .collect();
+
+        // Files of up to two blocks were hashed in full already.
+        let (mut sets, large): (Vec<_>, Vec<_>) = group(keyed)
+            .into_iter()
+            .partition(|((size, _), _)| *size <= 2 * EDGE_BLOCK);
+
+        let candidates: Vec<Candidate> = large.into_iter().flat_map(|(_, set)| set).collect();
+        self.begin(DuplicateStage::FullHash, candidates.len());
+        let keyed = candidates
+            .into_par_iter()
+            .filter_map(|file| {
+                let hash = self.step(self.full_hash(&file.path).ok())?;
+                Some(((file.size, hash), file))
+            })
+            .collect();
+        sets.extend(group(keyed));
+
+        let mut session = DuplicateSession::default();
+        for ((size, hash), set) in sets {
+            let hash = hash.to_hex().to_string();
+            for file in &set {
+                session
+                    .identities
+                    .insert(file.path.clone(), (file.identity, hash.clone()));
+            }
+            session.sets.push(DuplicateSet {
+                wasted: size * (set.len() as u64 - 1),
+                hash,
+                size,
+                files: set
+                    .into_iter()
+                    .map(|file| DuplicateFile {
+                        path: file.path.to_string_lossy().to_string(),
+                        modified: file.modified,
+                    })
+                    .collect(),
+            });
+        }
+        session.sets.sort_by_key(|set| std::cmp::Reverse(set.wasted));
+        self.report(true);
+        session
+    }
+
+    fn collect_root(&self, root: &Path) -> Vec<Candidate> {
+        match fs::symlink_metadata(root) {
+            Ok(metadata) if metadata.is_dir() => self.collect(root, 0, device_of(&metadata)),
+            _ => Vec::new(),
+        }
+    }
+
+    fn collect(&self, dir: &Path, depth: usize, device: Option<u64>) -> Vec<Candidate> {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(_) => return Vec::new(),
+        };
+        let now = now_millis();
+        let mut files = Vec::new();
+        let mut subdirs = Vec::new();
+
+        for entry in entries.flatten() {
+            if self.control.is_cancelled() {
+                break;
+            }
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+            self.control.record_entry(0);
+
+            if metadata.is_dir() && !metadata.file_type().is_symlink() {
+                if depth < DEFAULT_MAX_DEPTH && device_of(&metadata) == device {
+                    subdirs.push(entry.path());
+                }
+                continue;
+            }
+            if !metadata.is_file() || metadata.len() == 0 {
+                continue;
+            }
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            let modified = metadata.modified().ok().and_then(epoch_millis);
+            let created = metadata.created().ok().and_then(epoch_millis);
+            if !self.filters.matches_name(&name, &path)
+                || !self
+                    .filters
+                    .matches_stats(metadata.len(), modified, created, now)
+            {
+                continue;
+            }
+            self.files_total.fetch_add(1, Ordering::Relaxed);
+            self.report(false);
+            files.push(Candidate {
+                path,
+                size: metadata.len(),
+                modified,
+                identity: FileIdentity::of(&metadata),
+                inode: inode_of(&metadata),
+            });
+        }
+
+        files.par_extend(
+            subdirs
+                .par_iter()
+                .flat_map_iter(|subdir| self.collect(subdir, depth + 1, device)),
+        );
+        files
+    }
+
+    fn full_hash(&self, path: &Path) -> io::Result<blake3::Hash> {
+        let mut file = File::open(path)?;
+        let mut hasher = blake3::Hasher::new();
+        let mut buffer = vec![0u8; READ_BUFFER];
+        loop {
+            if self.control.is_cancelled() {
+                return Err(io::ErrorKind::Interrupted.into());
+            }
+            let read = file.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+            self.bytes_hashed.fetch_add(read as u64, Ordering::Relaxed);
+            self.report(false);
+        }
+        Ok(hasher.finalize())
+    }
+
+    fn begin(&self, stage: DuplicateStage, total: usize) {
+        if let Ok(mut current) = self.stage.lock() {
+            *current = stage;
+        }
+        self.files_done.store(0, Ordering::Relaxed);
+        if stage != DuplicateStage::Collecting {
+            self.files_total.store(total as u64, Ordering::Relaxed);
+        }
+        self.report(true);
+    }
+
+    /// Counts one file of the current stage as done.
+    fn step<T>(&self, value: Option<T>) -> Option<T> {
+        self.files_done.fetch_add(1, Ordering::Relaxed);
+        self.report(false);
+        value
+    }
+
+    fn report(&self, force: bool) {
+        if !self.throttle.ready(force) {
+            return;
+        }
+        self.observer.duplicate_progress(DuplicateProgress {
+            stage: self
+                .stage
+                .lock()
+                .map(|stage| *stage)
+                .unwrap_or(DuplicateStage::Collecting),
+            files_done: self.files_done.load(Ordering::Relaxed),
+            files_total: self.files_total.load(Ordering::Relaxed),
+            bytes_hashed: self.bytes_hashed.load(Ordering::Relaxed),
+        });
+    }
+}
+
+/// Groups items by key, dropping groups of a single item.
+fn group<K, T>(items: Vec<(K, T)>) -> Vec<(K, Vec<T>)>
+where
+    K: std::hash::Hash + Eq,
+{
+    let mut groups: HashMap<K, Vec<T>> = HashMap::new();
+    for (key, item) in items {
+        groups.entry(key).or_default().push(item);
+    }
+    groups.into_iter().filter(|(_, g)| g.len() > 1).collect()
+}
+
+/// Hash of the first and last [`EDGE_BLOCK`] bytes; the whole content for
+/// files of up to two blocks.
+fn partial_hash(path: &Path, size: u64) -> io::Result<blake3::Hash> {
+    let mut file = File::open(path)?;
+    let mut hasher = blake3::Hasher::new();
+    if size <= 2 * EDGE_BLOCK {
+        let mut content = Vec::with_capacity(size as usize);
+        file.read_to_end(&mut content)?;
+        hasher.update(&content);
+    } else {
+        let mut block = vec![0u8; EDGE_BLOCK as usize];
+        file.read_exact(&mut block)?;
+        hasher.update(&block);
+        file.seek(SeekFrom::End(-(EDGE_BLOCK as i64)))?;
+        file.read_exact(&mut block)?;
+        hasher.update(&block);
+    }
+    Ok(hasher.finalize())
+}
+
+fn content_hash(path: &Path) -> io::Result<String> {
+    let mut hasher = blake3::Hasher::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// How the extra copies of a set are dealt with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResolveMode {
+    /// Delete every copy but the one kept.
+    Delete,
+    /// Replace the copies with hard links to the one kept. They then share
+    /// its permissions and owner.
+    Hardlink,
+    /// Replace the copies with copy-on-write clones (Btrfs, XFS); Linux only.
+    Reflink,
+}
+
+/// Keep `keep` and resolve `remove`, all from the same duplicate set.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Resolution {
+    pub keep: String,
+    pub remove: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ResolveFailure {
+    pub path: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ResolveResult {
+    pub freed: u64,
+    pub resolved: Vec<String>,
+    pub failed: Vec<ResolveFailure>,
+}
+
+/// Duplicate sets of one `find_duplicates` run, with what each file looked
+/// like so changed files are never touched.
+#[derive(Debug, Default)]
+pub struct DuplicateSession {
+    pub sets: Vec<DuplicateSet>,
+    identities: HashMap<PathBuf, (FileIdentity, String)>,
+}
+
+impl DuplicateSession {
+    /// Applies `resolution`. Before anything is touched both files are
+    /// checked to be unchanged since the scan and hashed again.
+    pub fn resolve(
+        &self,
+        resolution: &Resolution,
+        mode: ResolveMode,
+        catalog: &Catalog,
+        result: &mut ResolveResult,
+    ) {
+        let keep = PathBuf::from(&resolution.keep);
+        let kept = self.verify(&keep, None);
+
+        for path_str in &resolution.remove {
+            let path = PathBuf::from(path_str);
+            let outcome = kept.clone().and_then(|(hash, size)| {
+                if path == keep {
+                    return Err("duplicate-is-kept");
+                }
+                self.verify(&path, Some(&hash))?;
+                apply(&keep, &path, mode).map_err(|e| match e.kind() {
+                    io::ErrorKind::Unsupported => "duplicate-link-unsupported",
+                    io::ErrorKind::CrossesDevices => "duplicate-other-device",
+                    _ => "duplicate-failed",
+                })?;
+                Ok(size)
+            });
+            match outcome {
+                Ok(size) => {
+                    result.freed += size;
+                    result.resolved.push(path_str.clone());
+                }
+                Err(key) => result.failed.push(ResolveFailure {
+                    path: path_str.clone(),
+                    reason: catalog.message(key),
+                }),
+            }
+        }
+    }
+
+    /// Checks that `path` was reported by this scan, hasn't been replaced
+    /// and still has the content it had then (and `hash`, if given).
+    /// Returns its hash and size.
+    fn verify(&self, path: &Path, hash: Option<&str>) -> Result<(String, u64), &'static str> {
+        let (identity, scanned_hash) = self.identities.get(path).ok_or("duplicate-not-scanned")?;
+        if hash.is_some_and(|hash| hash != scanned_hash) {
+            return Err("duplicate-not-scanned");
+        }
+        let metadata = fs::symlink_metadata(path).map_err(|_| "duplicate-missing")?;
+        if FileIdentity::of(&metadata) != *identity {
+            return Err("duplicate-changed");
+        }
+        match content_hash(path) {
+            Ok(current) if current == *scanned_hash => Ok((current, metadata.len())),
+            Ok(_) => Err("duplicate-changed"),
+            Err(_) => Err("duplicate-missing"),
+        }
+    }
+}
+
+fn apply(keep: &Path, copy: &Path, mode: ResolveMode) -> io::Result<()> {
+    if mode == ResolveMode::Delete {
+        return fs::remove_file(copy);
+    }
+
+    let same_device = match (fs::metadata(keep), fs::metadata(copy)) {
+        (Ok(a), Ok(b)) => device_of(&a) == device_of(&b),
+        (Err(e), _) | (_, Err(e)) => return Err(e),
+    };
+    if !same_device {
+        return Err(io::ErrorKind::CrossesDevices.into());
+    }
+
+    // Build the replacement next to the copy, then swap it in with a rename
+    // so the copy is never missing.
+    let staging = staging_path(copy);
+    let made = match mode {
+        ResolveMode::Hardlink => fs::hard_link(keep, &staging),
+        _ => reflink(keep, &staging, copy),
+    };
+    made.and_then(|_| fs::rename(&staging, copy)).inspect_err(|_| {
+        let _ = fs::remove_file(&staging);
+    })
+}
+
+fn staging_path(copy: &Path) -> PathBuf {
+    let name = copy
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    copy.with_file_name(format!(".{}.zcleaner-{}", name, uuid::Uuid::new_v4().simple()))
+}
+
+#[cfg(target_os = "linux")]
+fn reflink(source: &Path, target: &Path, copy: &Path) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    let source = File::open(source)?;
+    let target_file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(target)?;
+    // SAFETY: both descriptors are open for the duration of the call.
+    let status = unsafe { libc::ioctl(target_file.as_raw_fd(), libc::FICLONE, source.as_raw_fd()) };
+    if status != 0 {
+        let error = io::Error::last_os_error();
+        return Err(match error.raw_os_error() {
+            Some(libc::EOPNOTSUPP) | Some(libc::EINVAL) | Some(libc::ENOTTY) => {
+                io::ErrorKind::Unsupported.into()
+            }
+            Some(libc::EXDEV) => io::ErrorKind::CrossesDevices.into(),
+            _ => error,
+        });
+    }
+    fs::set_permissions(target, fs::metadata(copy)?.permissions())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn reflink(_source: &Path, _target: &Path, _copy: &Path) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
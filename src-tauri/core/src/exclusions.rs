@@ -0,0 +1,184 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::roots::RootResolver;
+
+/// File in the app config dir holding the user's exclusions.
+const EXCLUSIONS_FILE: &str = "exclusions.toml";
+
+/// A path or glob the scanner must never report and deletion must refuse.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Exclusion {
+    /// A path template like rule paths (`%TEMP%\tool`, `~/.cache/discord`)
+    /// protecting it and everything below it, or a glob when it contains
+    /// `*`, `?` or `[`.
+    pub path: String,
+    /// Category ids the exclusion applies to. Empty means every category.
+    #[serde(default)]
+    pub categories: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ExclusionFile {
+    #[serde(default)]
+    exclusions: Vec<Exclusion>,
+}
+
+#[derive(Debug, Clone)]
+enum Matcher {
+    Path(PathBuf),
+    /// The whole pattern, and one pattern per path component.
+    Glob(glob::Pattern, Vec<glob::Pattern>),
+}
+
+/// The user's exclusions, ready for matching.
+#[derive(Debug, Clone, Default)]
+pub struct Exclusions {
+    entries: Vec<(Exclusion, Option<Matcher>)>,
+    /// Expands the path templates of the entries.
+    resolver: RootResolver,
+}
+
+impl Exclusions {
+    pub fn new(entries: Vec<Exclusion>, resolver: &RootResolver) -> Result<Self, String> {
+        let entries = entries
+            .into_iter()
+            .map(|exclusion| {
+                let matcher = matcher(&exclusion.path, resolver)?;
+                Ok((exclusion, matcher))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Exclusions {
+            entries,
+            resolver: resolver.clone(),
+        })
+    }
+
+    /// Reads `exclusions.toml` from `config_dir`; none when it is missing.
+    pub fn load(config_dir: Option<&Path>, resolver: &RootResolver) -> Result<Self, String> {
+        let path = match config_dir {
+            Some(dir) => dir.join(EXCLUSIONS_FILE),
+            None => return Ok(Exclusions::default()),
+        };
+        if !path.is_file() {
+            return Ok(Exclusions::default());
+        }
+        let content = fs::read_to_string(&path)
+            .map_err(|e| format!("Erro ao ler {}: {}", path.display(), e))?;
+        let file: ExclusionFile = toml::from_str(&content)
+            .map_err(|e| format!("Exclusões inválidas em {}: {}", path.display(), e))?;
+        Exclusions::new(file.exclusions, resolver)
+    }
+
+    pub fn save(&self, config_dir: &Path) -> Result<(), String> {
+        let file = ExclusionFile {
+            exclusions: self.list(),
+        };
+        let content = toml::to_string(&file).map_err(|e| e.to_string())?;
+        fs::create_dir_all(config_dir)
+            .and_then(|_| fs::write(config_dir.join(EXCLUSIONS_FILE), content))
+            .map_err(|e| format!("Erro ao salvar exclusões: {}", e))
+    }
+
+    /// Adds `exclusion`, replacing the one with the same path if any.
+    pub fn add(&mut self, exclusion: Exclusion) -> Result<(), String> {
+        let matcher = matcher(&exclusion.path, &self.resolver)?;
+        match self.entries.iter_mut().find(|(e, _)| e.path == exclusion.path) {
+            Some(existing) => *existing = (exclusion, matcher),
+            None => self.entries.push((exclusion, matcher)),
+        }
+        Ok(())
+    }
+
+    /// Removes the exclusion with `path`; false when there was none.
+    pub fn remove(&mut self, path: &str) -> bool {
+        let before = self.entries.len();
+        self.entries.retain(|(e, _)| e.path != path);
+        self.entries.len() != before
+    }
+
+    pub fn list(&self) -> Vec<Exclusion> {
+        self.entries.iter().map(|(e, _)| e.clone()).collect()
+    }
+
+    /// The exclusion protecting `path`, reported under `category`, if any.
+    /// A protected path also protects every entry that contains it, since
+    /// deleting the entry would delete it too; for a glob, a directory is
+    /// protected when something below it could match.
+    pub fn matching(&self, path: &Path, is_dir: bool, category: &str) -> Option<&Exclusion> {
+        let target = normalize(path);
+        let options = glob::MatchOptions {
+            case_sensitive: !cfg!(windows),
+            ..Default::default()
+        };
+        self.entries
+            .iter()
+            .filter(|(exclusion, _)| {
+                exclusion.categories.is_empty()
+                    || exclusion.categories.iter().any(|c| c == category)
+            })
+            .find(|(_, matcher)| match matcher {
+                Some(Matcher::Path(protected)) => {
+                    target.starts_with(protected) || protected.starts_with(&target)
+                }
+                Some(Matcher::Glob(pattern, components)) => {
+                    pattern.matches_path_with(path, options)
+                        || (is_dir && matches_below(components, path, options))
+                }
+                None => false,
+            })
+            .map(|(exclusion, _)| exclusion)
+    }
+
+    pub fn excludes(&self, path: &Path, is_dir: bool, category: &str) -> bool {
+        self.matching(path, is_dir, category).is_some()
+    }
+}
+
+/// `None` when the template uses a variable that isn't set here; such an
+/// exclusion can't match anything on this machine.
+fn matcher(template: &str, resolver: &RootResolver) -> Result<Option<Matcher>, String> {
+    let path = match resolver.resolve(template) {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+    if template.contains(['*', '?', '[']) {
+        let invalid = |e: glob::PatternError| format!("Padrão inválido {}: {}", template, e);
+        let pattern = glob::Pattern::new(&path.to_string_lossy()).map_err(invalid)?;
+        let components = path
+            .components()
+            .map(|c| glob::Pattern::new(&c.as_os_str().to_string_lossy()))
+            .collect::<Result<_, _>>()
+            .map_err(invalid)?;
+        Ok(Some(Matcher::Glob(pattern, components)))
+    } else {
+        Ok(Some(Matcher::Path(normalize(&path))))
+    }
+}
+
+/// Whether a path below `dir` could match the glob split into `components`:
+/// each component of `dir` matches its own, and the glob goes deeper.
+fn matches_below(components: &[glob::Pattern], dir: &Path, options: glob::MatchOptions) -> bool {
+    let mut depth = 0;
+    for part in dir.components() {
+        match components.get(depth) {
+            // `**` matches any number of components, so anything below may.
+            Some(pattern) if pattern.as_str() == "**" => return true,
+            Some(pattern) if pattern.matches_with(&part.as_os_str().to_string_lossy(), options) => {
+                depth += 1;
+            }
+            _ => return false,
+        }
+    }
+    depth < components.len()
+}
+
+/// Paths compare case-insensitively on Windows.
+fn normalize(path: &Path) -> PathBuf {
+    if cfg!(windows) {
+        PathBuf::from(path.to_string_lossy().to_lowercase())
+    } else {
+        path.to_path_buf()
+    }
+}
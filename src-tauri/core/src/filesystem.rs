@@ -0,0 +1,331 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::filters::epoch_millis;
+use crate::session::FileIdentity;
+use crate::walk::{device_of, hard_links_of, inode_of};
+
+/// What the scanner and the cleaner need to know about an entry. Links are
+/// never followed, so `kind` is `Link` for the link itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    File,
+    Dir,
+    Link,
+    /// Sockets, pipes, devices; never reported.
+    Other,
+}
+
+#[derive(Debug, Clone)]
+pub struct Metadata {
+    pub kind: FileKind,
+    pub len: u64,
+    /// Milliseconds since the Unix epoch, when the platform provides them.
+    pub modified: Option<u64>,
+    pub accessed: Option<u64>,
+    pub created: Option<u64>,
+    pub identity: FileIdentity,
+    /// Used to stay on one file system; `None` where it isn't known.
+    pub device: Option<u64>,
+    /// Device and inode, used to catch directory loops and hard links.
+    pub inode: Option<(u64, u64)>,
+    /// Names the file goes by; 1 where it isn't known.
+    pub hard_links: u64,
+    /// Owning uid on unix.
+    pub owner: Option<u32>,
+    /// The read-only attribute on Windows; no write permission bits on unix.
+    pub readonly: bool,
+}
+
+impl Metadata {
+    pub fn is_dir(&self) -> bool {
+        self.kind == FileKind::Dir
+    }
+
+    /// Owned by the effective user; always true where owners aren't known.
+    pub fn is_owned_by_user(&self) -> bool {
+        self.owner.is_none_or(|uid| Some(uid) == effective_uid())
+    }
+}
+
+impl From<&fs::Metadata> for Metadata {
+    fn from(metadata: &fs::Metadata) -> Self {
+        let file_type = metadata.file_type();
+        let kind = if file_type.is_symlink() {
+            FileKind::Link
+        } else if file_type.is_dir() {
+            FileKind::Dir
+        } else if file_type.is_file() {
+            FileKind::File
+        } else {
+            FileKind::Other
+        };
+        Metadata {
+            kind,
+            len: metadata.len(),
+            modified: metadata.modified().ok().and_then(epoch_millis),
+            accessed: metadata.accessed().ok().and_then(epoch_millis),
+            created: metadata.created().ok().and_then(epoch_millis),
+            identity: FileIdentity::of(metadata),
+            device: device_of(metadata),
+            inode: inode_of(metadata),
+            hard_links: hard_links_of(metadata),
+            owner: owner_of(metadata),
+            readonly: metadata.permissions().readonly(),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn owner_of(metadata: &fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.uid())
+}
+
+#[cfg(not(unix))]
+fn owner_of(_metadata: &fs::Metadata) -> Option<u32> {
+    None
+}
+
+#[cfg(unix)]
+fn effective_uid() -> Option<u32> {
+    Some(unsafe { libc::geteuid() })
+}
+
+#[cfg(not(unix))]
+fn effective_uid() -> Option<u32> {
+    None
+}
+
+#[derive(Debug, Clone)]
+pub struct DirEntry {
+    pub path: PathBuf,
+    pub name: String,
+    pub metadata: Metadata,
+}
+
+/// The file operations behind scanning and cleaning, so they can run
+/// against something other than the local disk, or be observed in tests.
+pub trait FileSystem: fmt::Debug + Send + Sync {
+    /// Metadata of `path` itself, without following a final symlink.
+    fn symlink_metadata(&self, path: &Path) -> io::Result<Metadata>;
+
+    /// Metadata of what `path` points to.
+    fn metadata(&self, path: &Path) -> io::Result<Metadata>;
+
+    /// The entries of a directory; entries that can't be read are skipped.
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<DirEntry>>;
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf>;
+
+    fn remove_file(&self, path: &Path) -> io::Result<()>;
+
+    /// Removes an empty directory.
+    fn remove_dir(&self, path: &Path) -> io::Result<()>;
+
+    /// Whether the current user may remove `path` from its directory, as
+    /// far as permissions tell; used to predict failures without trying.
+    fn check_removable(&self, _path: &Path) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Makes `path` writable for its owner: clears the read-only attribute
+    /// on Windows, adds the owner's write bit on unix. Symlinks are followed.
+    fn clear_readonly(&self, _path: &Path) -> io::Result<()> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    /// Whether [`FileSystem::clear_readonly`] would be allowed on `path`,
+    /// without changing anything.
+    fn check_clear_readonly(&self, _path: &Path) -> io::Result<()> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    /// The operations below move entries in and out of the quarantine.
+    fn rename(&self, _from: &Path, _to: &Path) -> io::Result<()> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    fn create_dir_all(&self, _path: &Path) -> io::Result<()> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    /// Copies a regular file with its permissions.
+    fn copy_file(&self, _from: &Path, _to: &Path) -> io::Result<()> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    /// Creates `to` as a symlink with the same target as `from`.
+    fn copy_link(&self, _from: &Path, _to: &Path) -> io::Result<()> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    fn copy_permissions(&self, _from: &Path, _to: &Path) -> io::Result<()> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+}
+
+/// The local disk.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OsFs;
+
+impl FileSystem for OsFs {
+    fn symlink_metadata(&self, path: &Path) -> io::Result<Metadata> {
+        fs::symlink_metadata(path).map(|m| Metadata::from(&m))
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        fs::metadata(path).map(|m| Metadata::from(&m))
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<DirEntry>> {
+        Ok(fs::read_dir(path)?
+            .flatten()
+            .filter_map(|entry| {
+                // DirEntry::metadata does not traverse symlinks.
+                let metadata = entry.metadata().ok()?;
+                Some(DirEntry {
+                    path: entry.path(),
+                    name: entry.file_name().to_string_lossy().to_string(),
+                    metadata: Metadata::from(&metadata),
+                })
+            })
+            .collect())
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        fs::canonicalize(path)
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        fs::remove_file(path)
+    }
+
+    fn remove_dir(&self, path: &Path) -> io::Result<()> {
+        fs::remove_dir(path)
+    }
+
+    fn check_removable(&self, path: &Path) -> io::Result<()> {
+        check_removable(path)
+    }
+
+    fn clear_readonly(&self, path: &Path) -> io::Result<()> {
+        let mut permissions = fs::metadata(path)?.permissions();
+        set_writable(&mut permissions);
+        fs::set_permissions(path, permissions)
+    }
+
+    fn check_clear_readonly(&self, path: &Path) -> io::Result<()> {
+        check_clear_readonly(path)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        fs::rename(from, to)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        fs::create_dir_all(path)
+    }
+
+    fn copy_file(&self, from: &Path, to: &Path) -> io::Result<()> {
+        fs::copy(from, to).map(|_| ())
+    }
+
+    fn copy_link(&self, from: &Path, to: &Path) -> io::Result<()> {
+        copy_link(from, to)
+    }
+
+    fn copy_permissions(&self, from: &Path, to: &Path) -> io::Result<()> {
+        fs::set_permissions(to, fs::symlink_metadata(from)?.permissions())
+    }
+}
+
+#[cfg(unix)]
+fn copy_link(from: &Path, to: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(fs::read_link(from)?, to)
+}
+
+#[cfg(not(unix))]
+fn copy_link(_from: &Path, _to: &Path) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+#[cfg(unix)]
+fn set_writable(permissions: &mut fs::Permissions) {
+    use std::os::unix::fs::PermissionsExt;
+    // `set_readonly(false)` would make it writable for everyone.
+    permissions.set_mode(permissions.mode() | 0o200);
+}
+
+#[cfg(not(unix))]
+fn set_writable(permissions: &mut fs::Permissions) {
+    permissions.set_readonly(false);
+}
+
+#[cfg(unix)]
+fn check_removable(path: &Path) -> io::Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::fs::MetadataExt;
+
+    let parent = path
+        .parent()
+        .ok_or_else(|| io::Error::from(io::ErrorKind::InvalidInput))?;
+    // Unlinking takes write and search permission on the directory; a
+    // read-only mount fails here with EROFS.
+    let c_parent = CString::new(parent.as_os_str().as_bytes())?;
+    let mode = libc::W_OK | libc::X_OK;
+    if unsafe { libc::faccessat(libc::AT_FDCWD, c_parent.as_ptr(), mode, libc::AT_EACCESS) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // In sticky directories such as /tmp only the owner of the entry or of
+    // the directory may remove it.
+    const STICKY: u32 = 0o1000;
+    let dir = fs::metadata(parent)?;
+    if dir.mode() & STICKY != 0 {
+        let euid = unsafe { libc::geteuid() };
+        if euid != 0 && euid != dir.uid() && euid != fs::symlink_metadata(path)?.uid() {
+            return Err(io::ErrorKind::PermissionDenied.into());
+        }
+    }
+    Ok(())
+}
+
+/// Windows checks ACLs and the read-only attribute on removal; the latter
+/// is already in [`Metadata::readonly`].
+#[cfg(not(unix))]
+fn check_removable(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn check_clear_readonly(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::MetadataExt;
+
+    // Only the owner, or root, may change the mode.
+    let euid = unsafe { libc::geteuid() };
+    if euid == 0 || fs::metadata(path)?.uid() == euid {
+        Ok(())
+    } else {
+        Err(io::ErrorKind::PermissionDenied.into())
+    }
+}
+
+/// Opening `path` for writing its attributes is what clearing the read-only
+/// attribute takes; the handle is closed again untouched.
+#[cfg(not(unix))]
+fn check_clear_readonly(path: &Path) -> io::Result<()> {
+    use std::os::windows::fs::OpenOptionsExt;
+
+    /// FILE_WRITE_ATTRIBUTES
+    const WRITE_ATTRIBUTES: u32 = 0x0100;
+    /// FILE_FLAG_BACKUP_SEMANTICS, without which directories can't be opened.
+    const BACKUP_SEMANTICS: u32 = 0x0200_0000;
+    fs::OpenOptions::new()
+        .access_mode(WRITE_ATTRIBUTES)
+        .custom_flags(BACKUP_SEMANTICS)
+        .open(path)
+        .map(|_| ())
+}
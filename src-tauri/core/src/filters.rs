@@ -0,0 +1,126 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::SystemTime;
+
+use crate::TempFile;
+
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// Age, size and name constraints on reported entries. They come from three
+/// places — the rule itself, the `[filters]` table of the user rule file
+/// (team defaults) and the request — and an entry must pass all of them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EntryFilters {
+    /// Skip entries modified or created less than this many days ago. For
+    /// directories the newest entry inside counts.
+    #[serde(default)]
+    pub min_age_days: Option<u32>,
+    /// Skip entries smaller than this many bytes.
+    #[serde(default)]
+    pub min_size: Option<u64>,
+    /// Only keep entries matching one of these globs. Empty means every entry.
+    #[serde(default)]
+    pub include: Vec<NamePattern>,
+    /// Drop entries matching any of these globs.
+    #[serde(default)]
+    pub exclude: Vec<NamePattern>,
+}
+
+/// An `include`/`exclude` glob, compiled when the filters are read so that
+/// a bad pattern is reported instead of matching nothing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct NamePattern {
+    pattern: glob::Pattern,
+    /// Contains a path separator, so it is matched against the full path.
+    full_path: bool,
+}
+
+impl NamePattern {
+    pub fn new(pattern: &str) -> Result<Self, String> {
+        Ok(NamePattern {
+            pattern: glob::Pattern::new(pattern)
+                .map_err(|e| format!("Padrão inválido {}: {}", pattern, e))?,
+            full_path: pattern.contains(['/', '\\']),
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.pattern.as_str()
+    }
+
+    pub fn matches(&self, name: &str, path: &Path) -> bool {
+        if self.full_path {
+            self.pattern.matches(&path.to_string_lossy())
+        } else {
+            self.pattern.matches(name)
+        }
+    }
+}
+
+impl TryFrom<String> for NamePattern {
+    type Error = String;
+
+    fn try_from(pattern: String) -> Result<Self, String> {
+        NamePattern::new(&pattern)
+    }
+}
+
+impl From<NamePattern> for String {
+    fn from(pattern: NamePattern) -> String {
+        pattern.as_str().to_string()
+    }
+}
+
+impl EntryFilters {
+    /// Checks the include/exclude globs. Patterns containing a path
+    /// separator are matched against the full path, others against the name.
+    pub fn matches_name(&self, name: &str, path: &Path) -> bool {
+        let matches = |pattern: &NamePattern| pattern.matches(name, path);
+        (self.include.is_empty() || self.include.iter().any(matches))
+            && !self.exclude.iter().any(matches)
+    }
+
+    /// Checks the size and age limits. `now` is in milliseconds since the
+    /// Unix epoch.
+    pub fn matches_entry(&self, file: &TempFile, now: u64) -> bool {
+        self.matches_stats(file.size, file.modified, file.created, now)
+    }
+
+    /// [`EntryFilters::matches_entry`] for callers without a `TempFile`.
+    pub fn matches_stats(
+        &self,
+        size: u64,
+        modified: Option<u64>,
+        created: Option<u64>,
+        now: u64,
+    ) -> bool {
+        if self.min_size.is_some_and(|min| size < min) {
+            return false;
+        }
+        if let Some(days) = self.min_age_days {
+            let newest = modified.max(created);
+            if let Some(newest) = newest {
+                if now.saturating_sub(newest) < u64::from(days) * DAY_MS {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    pub fn matches(&self, file: &TempFile, now: u64) -> bool {
+        self.matches_name(&file.name, Path::new(&file.path)) && self.matches_entry(file, now)
+    }
+}
+
+/// Milliseconds since the Unix epoch, the unit of `TempFile` timestamps.
+pub fn epoch_millis(time: SystemTime) -> Option<u64> {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_millis() as u64)
+}
+
+pub fn now_millis() -> u64 {
+    epoch_millis(SystemTime::now()).unwrap_or(0)
+}
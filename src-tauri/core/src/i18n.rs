@@ -0,0 +1,98 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+
+const PT_BR: &str = include_str!("../locales/pt-BR.toml");
+const EN: &str = include_str!("../locales/en.toml");
+const ES: &str = include_str!("../locales/es.toml");
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Locale {
+    #[default]
+    #[serde(rename = "pt-BR")]
+    PtBr,
+    #[serde(rename = "en")]
+    En,
+    #[serde(rename = "es")]
+    Es,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoryText {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+/// Messages and category names for one locale. Keys missing from `en` or
+/// `es` fall back to pt-BR, and then to the key itself.
+#[derive(Debug, Default, Deserialize)]
+pub struct Catalog {
+    #[serde(default)]
+    messages: HashMap<String, String>,
+    #[serde(default)]
+    categories: HashMap<String, CategoryText>,
+}
+
+impl Catalog {
+    pub fn get(locale: Locale) -> &'static Catalog {
+        static PT_BR_CATALOG: OnceLock<Catalog> = OnceLock::new();
+        static EN_CATALOG: OnceLock<Catalog> = OnceLock::new();
+        static ES_CATALOG: OnceLock<Catalog> = OnceLock::new();
+
+        let (cell, source) = match locale {
+            Locale::PtBr => (&PT_BR_CATALOG, PT_BR),
+            Locale::En => (&EN_CATALOG, EN),
+            Locale::Es => (&ES_CATALOG, ES),
+        };
+        cell.get_or_init(|| toml::from_str(source).expect("built-in catalogs must be valid TOML"))
+    }
+
+    fn fallback() -> &'static Catalog {
+        Catalog::get(Locale::PtBr)
+    }
+
+    pub fn message(&self, key: &str) -> String {
+        self.messages
+            .get(key)
+            .or_else(|| Catalog::fallback().messages.get(key))
+            .cloned()
+            .unwrap_or_else(|| key.to_string())
+    }
+
+    /// Like [`Catalog::message`], replacing `{name}` placeholders with `args`.
+    pub fn format(&self, key: &str, args: &[(&str, String)]) -> String {
+        args.iter()
+            .fold(self.message(key), |text, (name, value)| {
+                text.replace(&format!("{{{}}}", name), value)
+            })
+    }
+
+    pub fn category(&self, id: &str) -> Option<&CategoryText> {
+        self.categories
+            .get(id)
+            .or_else(|| Catalog::fallback().categories.get(id))
+    }
+}
+
+/// The locale picked in the UI; pt-BR until `set_locale` is called.
+#[derive(Default)]
+pub struct I18n {
+    locale: RwLock<Locale>,
+}
+
+impl I18n {
+    pub fn locale(&self) -> Locale {
+        self.locale.read().map(|l| *l).unwrap_or_default()
+    }
+
+    pub fn set_locale(&self, locale: Locale) {
+        if let Ok(mut current) = self.locale.write() {
+            *current = locale;
+        }
+    }
+
+    pub fn catalog(&self) -> &'static Catalog {
+        Catalog::get(self.locale())
+    }
+}
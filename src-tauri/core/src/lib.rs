@@ -0,0 +1,585 @@
+//! Scanning, cleaning, process and system information behind zCleaner,
+//! shared by the desktop app and `zcleaner-cli`. Long operations report
+//! through a [`ProgressObserver`] instead of a GUI event bus.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use sysinfo::{Disks, System};
+
+pub mod artifacts;
+pub mod browsers;
+pub mod cleaning;
+pub mod delete;
+pub mod duplicates;
+pub mod exclusions;
+pub mod filesystem;
+pub mod filters;
+pub mod i18n;
+pub mod open_files;
+pub mod progress;
+pub mod quarantine;
+pub mod report;
+pub mod roots;
+pub mod rules;
+pub mod scan;
+pub mod session;
+pub mod usage;
+pub mod walk;
+
+use cleaning::DeleteControl;
+use filters::EntryFilters;
+use i18n::Catalog;
+use open_files::{FileHolder, OpenFiles};
+use quarantine::Quarantine;
+use delete::{FailureKind, RemovalFailure, RemovalPlan, Removed};
+use rules::{CleaningRule, RuleRegistry};
+use scan::{ScanControl, ScanPartial};
+use session::{FileIdentity, RejectedPath, Rejection, ScanSession};
+
+pub use progress::ProgressObserver;
+
+#[cfg(target_os = "windows")]
+pub fn is_elevated() -> bool {
+    use std::process::Command;
+    
+    let output = Command::new("net")
+        .args(&["session"])
+        .output();
+    
+    match output {
+        Ok(output) => output.status.success(),
+        Err(_) => false,
+    }
+}
+
+#[cfg(unix)]
+pub fn is_elevated() -> bool {
+    unsafe { libc::geteuid() == 0 }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryKind {
+    File,
+    Dir,
+    /// A symlink (or junction); only the link itself is ever removed.
+    Link,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TempFile {
+    pub path: String,
+    pub name: String,
+    pub size: u64,
+    pub kind: EntryKind,
+    /// Stable id such as `chrome-cache`; match on this, not on `category`.
+    pub category_id: String,
+    /// Category name in the selected locale.
+    pub category: String,
+    pub root_path: String,
+    /// Timestamps in milliseconds since the Unix epoch, when the platform
+    /// provides them. For directories `modified` is the newest modification
+    /// time of anything inside.
+    pub modified: Option<u64>,
+    pub accessed: Option<u64>,
+    pub created: Option<u64>,
+    /// Kept server-side only, see [`FileIdentity`].
+    #[serde(skip)]
+    pub identity: Option<FileIdentity>,
+    /// Processes that had the entry, or something inside it, open at scan
+    /// time.
+    #[serde(default)]
+    pub in_use: Vec<FileHolder>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScanResult {
+    /// Pass this back to `delete_temp_files`; only entries of this scan can
+    /// be deleted.
+    pub session_id: String,
+    pub files: Vec<TempFile>,
+    pub total_size: u64,
+    /// True when `cancel_scan` stopped the scan; `files` holds what was found
+    /// until then.
+    #[serde(default)]
+    pub cancelled: bool,
+    /// When the scan finished, in milliseconds since the Unix epoch.
+    #[serde(default)]
+    pub scanned_at: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeleteProgress {
+    pub current: usize,
+    pub total: usize,
+    /// Weighted by the scanned size of each path, so one large folder
+    /// counts for more than many small files.
+    pub percentage: f32,
+    pub deleted_size: u64,
+    /// What was actually removed so far, partial deletions included.
+    pub freed_bytes: u64,
+    /// Scanned size of the paths handled so far, and of all of them.
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    pub removed_files: u64,
+    pub files_per_second: f64,
+    /// Seconds left at the pace so far; `None` until there is one.
+    pub eta_seconds: Option<u64>,
+    /// How the path just handled went.
+    pub item: ItemReport,
+}
+
+/// What became of one requested path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Deleted,
+    /// Removal stopped at an error after part of the entry was gone.
+    PartiallyDeleted,
+    /// Refused by the checks before deletion; nothing was touched.
+    Skipped,
+    Failed,
+}
+
+impl Outcome {
+    /// Whether something the user asked to delete is still there.
+    pub fn is_incomplete(self) -> bool {
+        matches!(self, Outcome::PartiallyDeleted | Outcome::Failed)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemReport {
+    pub path: String,
+    pub outcome: Outcome,
+    /// Why the path was skipped or not fully removed.
+    pub error: Option<FailureKind>,
+    /// The check a skipped path failed.
+    pub rejection: Option<Rejection>,
+    pub files_removed: u64,
+    pub bytes_freed: u64,
+    /// What was left behind, see [`Removed::failures`].
+    #[serde(default)]
+    pub failures: Vec<RemovalFailure>,
+}
+
+impl ItemReport {
+    fn removal(path: &str, removed: Removed) -> ItemReport {
+        let outcome = if removed.is_complete() {
+            Outcome::Deleted
+        } else if removed.is_empty() {
+            Outcome::Failed
+        } else {
+            Outcome::PartiallyDeleted
+        };
+        ItemReport {
+            path: path.to_string(),
+            outcome,
+            error: removed.first_failure(),
+            rejection: None,
+            files_removed: removed.files,
+            bytes_freed: removed.bytes,
+            failures: removed.failures,
+        }
+    }
+
+    fn skipped(path: &str, rejection: Rejection) -> ItemReport {
+        let error = match rejection {
+            Rejection::Missing => Some(FailureKind::NotFound),
+            Rejection::InUse => Some(FailureKind::InUse),
+            Rejection::Excluded => Some(FailureKind::Excluded),
+            _ => None,
+        };
+        ItemReport {
+            path: path.to_string(),
+            outcome: Outcome::Skipped,
+            error,
+            rejection: Some(rejection),
+            files_removed: 0,
+            bytes_freed: 0,
+            failures: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeleteReport {
+    /// Scanned size of the entries fully deleted.
+    pub deleted_size: u64,
+    /// Entries removed, with their size at deletion time.
+    #[serde(default)]
+    pub deleted: Vec<TempFile>,
+    /// Paths that were refused and left untouched.
+    pub rejected: Vec<RejectedPath>,
+    /// Every path handled, in request order.
+    #[serde(default)]
+    pub items: Vec<ItemReport>,
+    /// What was actually removed, partial deletions included.
+    #[serde(default)]
+    pub freed_bytes: u64,
+    #[serde(default)]
+    pub removed_files: u64,
+    /// When the run finished, in milliseconds since the Unix epoch.
+    #[serde(default)]
+    pub finished_at: Option<u64>,
+    /// Entries were moved into the quarantine rather than removed.
+    #[serde(default)]
+    pub quarantined: bool,
+    /// The run was cancelled; the paths after the last one in `items` were
+    /// left alone.
+    #[serde(default)]
+    pub cancelled: bool,
+    /// Set by [`plan_entries`]: nothing was removed, `planned` says what
+    /// would be.
+    #[serde(default)]
+    pub dry_run: bool,
+    #[serde(default)]
+    pub planned: Vec<PlannedEntry>,
+}
+
+/// An entry that passed every check in a dry run, with what removing it
+/// would do.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannedEntry {
+    pub entry: TempFile,
+    pub removal: RemovalPlan,
+}
+
+/// Scans every applicable rule of `registry`, handing each rule's entries to
+/// `observer` as soon as it is done. Entries are marked with the processes
+/// holding them open.
+pub fn scan_entries(
+    registry: &RuleRegistry,
+    request: &EntryFilters,
+    control: &ScanControl,
+    observer: &dyn ProgressObserver,
+) -> Vec<TempFile> {
+    let open_files = OpenFiles::snapshot();
+    let mut files = registry.scan(request, control, |rule, files| {
+        let mut files = files.to_vec();
+        open_files.mark(&mut files);
+        observer.scan_partial(ScanPartial {
+            rule_id: rule.id.clone(),
+            category_id: rule.category.clone(),
+            category: rule.category_name().to_string(),
+            total_size: files.iter().map(|f| f.size).sum(),
+            files,
+        });
+    });
+    open_files.mark(&mut files);
+    files
+}
+
+/// Deletes `paths` from `session`, refusing every entry that no longer
+/// passes [`ScanSession::validate`] or that a process has open, unless
+/// `include_in_use` is set. With a `quarantine` the entries are moved there
+/// instead, and its retention is applied afterwards. A cancel through
+/// `control` stops the run before the next path.
+pub fn delete_entries(
+    registry: &RuleRegistry,
+    session: &ScanSession,
+    paths: &[String],
+    request: &EntryFilters,
+    include_in_use: bool,
+    mut quarantine: Option<&mut Quarantine>,
+    control: &DeleteControl,
+) -> DeleteReport {
+    // Files may have been opened or closed since the scan, so look again.
+    let open_files = if include_in_use {
+        OpenFiles::default()
+    } else {
+        OpenFiles::snapshot()
+    };
+    let checks = ScanControl::default();
+    let mut deleted_size = 0u64;
+    let mut deleted = Vec::new();
+    let mut rejected = Vec::new();
+    let mut items = Vec::new();
+    let (mut freed_bytes, mut removed_files) = (0u64, 0u64);
+    let total = paths.len();
+    let scanned_size = |path: &str| session.entry(Path::new(path)).map_or(0, |e| e.size);
+    let bytes_total: u64 = paths.iter().map(|path| scanned_size(path)).sum();
+    let mut bytes_done = 0u64;
+    let started = Instant::now();
+
+    for (index, path_str) in paths.iter().enumerate() {
+        if control.is_cancelled() {
+            break;
+        }
+        let path = PathBuf::from(&path_str);
+        let item = match check_entry(registry, session, &open_files, &path, request, &checks) {
+            Ok((entry, rule)) => {
+                let removed = match quarantine.as_deref_mut() {
+                    Some(quarantine) => quarantine.store(registry, entry.clone()),
+                    None => {
+                        delete::remove_path(registry.filesystem(), &path, rule.one_file_system)
+                    }
+                };
+                if removed.is_complete() {
+                    deleted_size += entry.size;
+                    deleted.push(entry);
+                }
+                ItemReport::removal(path_str, removed)
+            }
+            Err(reason) => {
+                rejected.push(RejectedPath {
+                    path: path_str.clone(),
+                    reason,
+                });
+                ItemReport::skipped(path_str, reason)
+            }
+        };
+        freed_bytes += item.bytes_freed;
+        removed_files += item.files_removed;
+        bytes_done += scanned_size(path_str);
+
+        let done = if bytes_total > 0 {
+            bytes_done as f32 / bytes_total as f32
+        } else {
+            (index + 1) as f32 / total as f32
+        };
+        let elapsed = started.elapsed().as_secs_f64();
+        let eta_seconds = (elapsed > 0.0 && bytes_done > 0).then(|| {
+            let pace = bytes_done as f64 / elapsed;
+            ((bytes_total - bytes_done) as f64 / pace).ceil() as u64
+        });
+        control.report(DeleteProgress {
+            current: index + 1,
+            total,
+            percentage: done * 100.0,
+            deleted_size,
+            freed_bytes,
+            bytes_done,
+            bytes_total,
+            removed_files,
+            files_per_second: if elapsed > 0.0 { removed_files as f64 / elapsed } else { 0.0 },
+            eta_seconds,
+            item: item.clone(),
+        });
+        items.push(item);
+    }
+
+    let cancelled = items.len() < total;
+    let quarantined = quarantine.is_some();
+    if let Some(quarantine) = quarantine {
+        quarantine.expire(registry.filesystem());
+    }
+
+    DeleteReport {
+        deleted_size,
+        deleted,
+        rejected,
+        items,
+        freed_bytes,
+        removed_files,
+        finished_at: Some(filters::now_millis()),
+        quarantined,
+        cancelled,
+        dry_run: false,
+        planned: Vec::new(),
+    }
+}
+
+/// Dry run of [`delete_entries`]: the same checks, then a walk of what each
+/// accepted entry would remove with the failures to expect. Nothing is
+/// touched.
+pub fn plan_entries(
+    registry: &RuleRegistry,
+    session: &ScanSession,
+    paths: &[String],
+    request: &EntryFilters,
+    include_in_use: bool,
+) -> DeleteReport {
+    let open_files = OpenFiles::snapshot();
+    let unchecked = OpenFiles::default();
+    let control = ScanControl::default();
+    let mut planned = Vec::new();
+    let mut rejected = Vec::new();
+
+    for path_str in paths {
+        let path = PathBuf::from(&path_str);
+        let checked_open = if include_in_use {
+            &unchecked
+        } else {
+            &open_files
+        };
+        match check_entry(registry, session, checked_open, &path, request, &control) {
+            Ok((mut entry, rule)) => {
+                let mut removal =
+                    delete::plan_removal(registry.filesystem(), &path, rule.one_file_system);
+                entry.in_use = open_files.holders(&path);
+                // Windows refuses to remove open files.
+                if cfg!(windows) && !entry.in_use.is_empty() {
+                    removal.fail(&path, FailureKind::InUse);
+                }
+                planned.push(PlannedEntry { entry, removal });
+            }
+            Err(reason) => rejected.push(RejectedPath {
+                path: path_str.clone(),
+                reason,
+            }),
+        }
+    }
+
+    DeleteReport {
+        deleted_size: 0,
+        deleted: Vec::new(),
+        rejected,
+        items: Vec::new(),
+        freed_bytes: 0,
+        removed_files: 0,
+        finished_at: Some(filters::now_millis()),
+        quarantined: false,
+        cancelled: false,
+        dry_run: true,
+        planned,
+    }
+}
+
+/// Only entries of the scan, still inside their cleaning root, unchanged
+/// since and not held open by a process in `open_files` are ever touched.
+fn check_entry<'a>(
+    registry: &'a RuleRegistry,
+    session: &'a ScanSession,
+    open_files: &OpenFiles,
+    path: &Path,
+    request: &EntryFilters,
+    control: &ScanControl,
+) -> Result<(TempFile, &'a CleaningRule), Rejection> {
+    session
+        .validate(registry, path, request, control)
+        .and_then(|found| {
+            if open_files.holders(path).is_empty() {
+                Ok(found)
+            } else {
+                Err(Rejection::InUse)
+            }
+        })
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SystemInfo {
+    pub cpu_name: String,
+    pub cpu_cores: usize,
+    pub total_memory: u64,
+    pub used_memory: u64,
+    pub os_name: String,
+    pub os_version: String,
+    pub kernel_version: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DiskInfo {
+    pub name: String,
+    pub mount_point: String,
+    pub total_space: u64,
+    pub available_space: u64,
+    pub is_removable: bool,
+    pub file_system: String,
+}
+
+pub fn system_info(catalog: &Catalog) -> SystemInfo {
+    let unknown = || catalog.message("unknown");
+    let mut sys = System::new_all();
+    sys.refresh_all();
+
+    let cpu_name = sys
+        .cpus()
+        .first()
+        .map(|cpu| cpu.brand().to_string())
+        .unwrap_or_else(unknown);
+
+    let cpu_cores = sys.cpus().len();
+    let total_memory = sys.total_memory();
+    let used_memory = sys.used_memory();
+
+    let os_name = System::name().unwrap_or_else(unknown);
+    let os_version = System::os_version().unwrap_or_else(unknown);
+    let kernel_version = System::kernel_version().unwrap_or_else(unknown);
+
+    SystemInfo {
+        cpu_name,
+        cpu_cores,
+        total_memory,
+        used_memory,
+        os_name,
+        os_version,
+        kernel_version,
+    }
+}
+
+pub fn disk_info() -> Vec<DiskInfo> {
+    let disks = Disks::new_with_refreshed_list();
+    let mut disk_list = Vec::new();
+
+    for disk in disks.list() {
+        disk_list.push(DiskInfo {
+            name: disk.name().to_string_lossy().to_string(),
+            mount_point: disk.mount_point().to_string_lossy().to_string(),
+            total_space: disk.total_space(),
+            available_space: disk.available_space(),
+            is_removable: disk.is_removable(),
+            file_system: disk.file_system().to_string_lossy().to_string(),
+        });
+    }
+
+    disk_list
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub name: String,
+    pub cpu_usage: f32,
+    pub memory: u64,
+    pub disk_usage: u64,
+    pub status: String,
+}
+
+pub fn list_processes(catalog: &Catalog) -> Vec<ProcessInfo> {
+    let mut sys = System::new_all();
+    sys.refresh_all();
+    
+    let cpu_count = sys.cpus().len() as f32;
+    let mut processes = Vec::new();
+    
+    for (pid, process) in sys.processes() {
+        let status = if process.status().to_string().contains("Run") {
+            catalog.message("process-running")
+        } else if process.status().to_string().contains("Sleep") {
+            catalog.message("process-sleeping")
+        } else {
+            process.status().to_string()
+        };
+        
+        // Normalizar CPU usage dividindo pelo número de núcleos
+        let cpu_usage = process.cpu_usage() / cpu_count;
+        
+        processes.push(ProcessInfo {
+            pid: pid.as_u32(),
+            name: process.name().to_string_lossy().to_string(),
+            cpu_usage,
+            memory: process.memory(),
+            disk_usage: process.disk_usage().total_read_bytes + process.disk_usage().total_written_bytes,
+            status,
+        });
+    }
+    
+    processes
+}
+
+pub fn terminate_process(catalog: &Catalog, pid: u32) -> Result<bool, String> {
+    let mut sys = System::new_all();
+    sys.refresh_all();
+    
+    if let Some(process) = sys.process(sysinfo::Pid::from_u32(pid)) {
+        if process.kill() {
+            Ok(true)
+        } else {
+            Err(catalog.message("process-kill-failed"))
+        }
+    } else {
+        Err(catalog.message("process-not-found"))
+    }
+}
@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use sysinfo::{ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind};
+
+use crate::TempFile;
+
+/// A running process that holds a file open.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileHolder {
+    pub pid: u32,
+    pub name: String,
+}
+
+/// Paths held by running processes, taken from the same process table as
+/// `get_processes`: every executable and working directory and, on Linux,
+/// everything listed in `/proc/<pid>/fd`. Processes of other users are only
+/// visible with enough privileges.
+#[derive(Debug, Default)]
+pub struct OpenFiles {
+    paths: BTreeMap<PathBuf, Vec<FileHolder>>,
+}
+
+impl OpenFiles {
+    pub fn snapshot() -> Self {
+        let mut sys = System::new();
+        sys.refresh_processes_specifics(
+            ProcessesToUpdate::All,
+            true,
+            ProcessRefreshKind::new()
+                .with_exe(UpdateKind::OnlyIfNotSet)
+                .with_cwd(UpdateKind::OnlyIfNotSet),
+        );
+
+        let mut open = OpenFiles::default();
+        for (pid, process) in sys.processes() {
+            let holder = FileHolder {
+                pid: pid.as_u32(),
+                name: process.name().to_string_lossy().to_string(),
+            };
+            for path in process.exe().into_iter().chain(process.cwd()) {
+                open.add(path.to_path_buf(), &holder);
+            }
+            for path in descriptors(holder.pid) {
+                open.add(path, &holder);
+            }
+        }
+        open
+    }
+
+    fn add(&mut self, path: PathBuf, holder: &FileHolder) {
+        let holders = self.paths.entry(path).or_default();
+        if !holders.contains(holder) {
+            holders.push(holder.clone());
+        }
+    }
+
+    /// Processes holding `path` itself or, for a directory, anything below it.
+    pub fn holders(&self, path: &Path) -> Vec<FileHolder> {
+        // Paths order component-wise, so everything below `path` directly
+        // follows it.
+        let mut holders: Vec<FileHolder> = Vec::new();
+        for (_, found) in self
+            .paths
+            .range(path.to_path_buf()..)
+            .take_while(|(open, _)| open.starts_with(path))
+        {
+            for holder in found {
+                if !holders.contains(holder) {
+                    holders.push(holder.clone());
+                }
+            }
+        }
+        holders
+    }
+
+    /// Fills in `in_use` for each of `files`.
+    pub fn mark(&self, files: &mut [TempFile]) {
+        for file in files {
+            file.in_use = self.holders(Path::new(&file.path));
+        }
+    }
+}
+
+/// Files a process has open. Sockets, pipes and other anonymous descriptors
+/// are skipped; unlinked files keep their old path with a " (deleted)" suffix
+/// and can't match anything on disk anyway.
+#[cfg(target_os = "linux")]
+fn descriptors(pid: u32) -> Vec<PathBuf> {
+    let entries = match std::fs::read_dir(format!("/proc/{}/fd", pid)) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| std::fs::read_link(entry.path()).ok())
+        .filter(|target| target.is_absolute())
+        .collect()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn descriptors(_pid: u32) -> Vec<PathBuf> {
+    Vec::new()
+}
@@ -0,0 +1,19 @@
+use crate::duplicates::DuplicateProgress;
+use crate::scan::{ScanProgress, ScanPartial};
+use crate::DeleteProgress;
+
+/// Receives progress of long operations: the desktop app turns these into
+/// events, the CLI ignores them. Every method does nothing by default.
+pub trait ProgressObserver: Send + Sync {
+    fn scan_progress(&self, _progress: ScanProgress) {}
+
+    /// Entries of one rule, as soon as the rule is scanned.
+    fn scan_partial(&self, _partial: ScanPartial) {}
+
+    fn delete_progress(&self, _progress: DeleteProgress) {}
+
+    fn duplicate_progress(&self, _progress: DuplicateProgress) {}
+}
+
+/// Ignores all progress.
+impl ProgressObserver for () {}
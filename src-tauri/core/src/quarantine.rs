@@ -0,0 +1,458 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::delete::{remove_path, FailureKind, Removed};
+use crate::filesystem::{FileKind, FileSystem};
+use crate::filters::now_millis;
+use crate::i18n::Catalog;
+use crate::rules::RuleRegistry;
+use crate::scan::ScanControl;
+use crate::walk::Walker;
+use crate::TempFile;
+
+/// Folder in the app data dir holding the manifest and the items moved from
+/// the volume the app data lives on.
+const QUARANTINE_DIR: &str = "quarantine";
+const MANIFEST_FILE: &str = "manifest.json";
+/// Locked by whoever has the quarantine open, the app or the CLI.
+const LOCK_FILE: &str = "manifest.lock";
+/// Created at the top of other volumes, like the freedesktop `.Trash-$uid`,
+/// so their items are renamed into place instead of copied.
+const VOLUME_DIR: &str = ".zcleaner-quarantine";
+
+const DAY_MILLIS: u64 = 24 * 60 * 60 * 1000;
+
+/// How long quarantined items are kept before they are purged for good.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Retention {
+    /// Items quarantined longer ago than this are purged. `None` keeps them.
+    #[serde(default)]
+    pub max_age_days: Option<u32>,
+    /// Once the quarantine holds more than this many bytes, the oldest
+    /// items are purged. `None` means no limit.
+    #[serde(default)]
+    pub max_size: Option<u64>,
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Retention {
+            max_age_days: Some(30),
+            max_size: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuarantineItem {
+    pub id: String,
+    /// The entry as it was scanned; `path` is where it is restored to.
+    pub entry: TempFile,
+    /// Where the entry is kept meanwhile.
+    pub stored_path: String,
+    /// Milliseconds since the Unix epoch.
+    pub quarantined_at: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    #[serde(default)]
+    retention: Retention,
+    #[serde(default)]
+    items: Vec<QuarantineItem>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QuarantineList {
+    /// Most recent first.
+    pub items: Vec<QuarantineItem>,
+    pub total_size: u64,
+    pub retention: Retention,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RestoreFailure {
+    pub id: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct RestoreResult {
+    pub restored: Vec<QuarantineItem>,
+    pub failed: Vec<RestoreFailure>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct PurgeResult {
+    pub purged: Vec<QuarantineItem>,
+    /// Bytes given back to the disk.
+    pub freed: u64,
+}
+
+/// Cleaned entries kept aside so an over-eager cleanup can be undone. The
+/// manifest records each entry's original path and scan metadata; entries
+/// are moved with a rename whenever the volume allows it.
+#[derive(Debug)]
+pub struct Quarantine {
+    dir: PathBuf,
+    manifest: Manifest,
+    /// Held until the quarantine is dropped, so no other process or thread
+    /// changes the manifest in the meantime.
+    _lock: fs::File,
+}
+
+impl Quarantine {
+    /// Opens the quarantine kept in `data_dir`; empty when there is none yet.
+    /// Waits while someone else, in this process or another, has it open.
+    pub fn open(data_dir: &Path) -> Result<Self, String> {
+        let dir = data_dir.join(QUARANTINE_DIR);
+        let lock_path = dir.join(LOCK_FILE);
+        let lock = fs::create_dir_all(&dir)
+            .and_then(|_| {
+                fs::OpenOptions::new()
+                    .create(true)
+                    .truncate(false)
+                    .write(true)
+                    .open(&lock_path)
+            })
+            .and_then(|file| file.lock().map(|_| file))
+            .map_err(|e| format!("Erro ao bloquear {}: {}", lock_path.display(), e))?;
+        Ok(Quarantine {
+            manifest: read_manifest(&dir)?,
+            dir,
+            _lock: lock,
+        })
+    }
+
+    /// Lists the quarantine kept in `data_dir` without waiting for whoever
+    /// has it open; the manifest is only ever replaced whole.
+    pub fn list_in(data_dir: &Path) -> Result<QuarantineList, String> {
+        let dir = data_dir.join(QUARANTINE_DIR);
+        Ok(list(&read_manifest(&dir)?))
+    }
+
+    pub fn list(&self) -> QuarantineList {
+        list(&self.manifest)
+    }
+
+    pub fn total_size(&self) -> u64 {
+        self.manifest.total_size()
+    }
+
+    /// Changes the retention and purges what it no longer keeps.
+    pub fn set_retention(
+        &mut self,
+        fs: &dyn FileSystem,
+        retention: Retention,
+    ) -> Result<PurgeResult, String> {
+        self.manifest.retention = retention;
+        self.save()
+            .map_err(|e| format!("Erro ao salvar a quarentena: {}", e))?;
+        Ok(self.expire(fs))
+    }
+
+    /// Moves `entry` into the quarantine through the registry's file
+    /// system. The manifest is written before the move, so an interrupted
+    /// move never leaves an item nobody knows about. Returns what was taken
+    /// off the entry's original location: when a copy to another volume
+    /// succeeds but the original can't be fully removed, the item is kept
+    /// and the rest reported as failures.
+    pub fn store(&mut self, registry: &RuleRegistry, entry: TempFile) -> Removed {
+        let fs = registry.filesystem();
+        let source = PathBuf::from(&entry.path);
+        let mut removed = Removed::default();
+        let Some(name) = source.file_name() else {
+            removed.fail(&source, FailureKind::Other);
+            return removed;
+        };
+        let id = uuid::Uuid::new_v4().to_string();
+        let item_dir = self.store_for(registry, &source).join(&id);
+        let target = item_dir.join(name);
+
+        self.manifest.items.push(QuarantineItem {
+            id: id.clone(),
+            stored_path: target.to_string_lossy().to_string(),
+            quarantined_at: now_millis(),
+            entry,
+        });
+        let moved = self
+            .save()
+            .and_then(|_| fs.create_dir_all(&item_dir))
+            .and_then(|_| move_path(fs, &source, &target));
+        match moved {
+            Ok(moved) => moved,
+            Err(error) => {
+                let _ = fs.remove_dir(&item_dir);
+                self.manifest.items.retain(|item| item.id != id);
+                let _ = self.save();
+                removed.fail(&source, FailureKind::of(&error));
+                removed
+            }
+        }
+    }
+
+    /// Moves the items with `ids` back to where they were. An item whose
+    /// original path was taken again in the meantime is left in quarantine.
+    pub fn restore(
+        &mut self,
+        fs: &dyn FileSystem,
+        ids: &[String],
+        catalog: &Catalog,
+    ) -> RestoreResult {
+        let mut result = RestoreResult::default();
+        for id in ids {
+            let fail = |key: &str| RestoreFailure {
+                id: id.clone(),
+                reason: catalog.message(key),
+            };
+            let Some(index) = self.manifest.items.iter().position(|item| &item.id == id) else {
+                result.failed.push(fail("quarantine-unknown"));
+                continue;
+            };
+            let item = &self.manifest.items[index];
+            let stored = PathBuf::from(&item.stored_path);
+            let original = PathBuf::from(&item.entry.path);
+
+            if fs.symlink_metadata(&stored).is_err() {
+                result.failed.push(fail("quarantine-missing"));
+                continue;
+            }
+            if fs.symlink_metadata(&original).is_ok() {
+                result.failed.push(fail("quarantine-target-exists"));
+                continue;
+            }
+            let moved = match original.parent() {
+                Some(parent) => {
+                    fs.create_dir_all(parent).and_then(|_| move_path(fs, &stored, &original))
+                }
+                None => Err(io::ErrorKind::InvalidInput.into()),
+            };
+            match moved {
+                Ok(_) => {
+                    // Also takes what a copy back left behind.
+                    if let Some(item_dir) = stored.parent() {
+                        remove_path(fs, item_dir, false);
+                    }
+                    result.restored.push(self.manifest.items.remove(index));
+                }
+                Err(_) => result.failed.push(fail("quarantine-restore-failed")),
+            }
+        }
+        let _ = self.save();
+        result
+    }
+
+    /// Deletes the items with `ids` for good, or every item when `ids` is
+    /// `None`.
+    pub fn purge(&mut self, fs: &dyn FileSystem, ids: Option<&[String]>) -> PurgeResult {
+        let doomed: Vec<String> = self
+            .manifest
+            .items
+            .iter()
+            .filter(|item| ids.is_none_or(|ids| ids.contains(&item.id)))
+            .map(|item| item.id.clone())
+            .collect();
+        self.purge_ids(fs, &doomed)
+    }
+
+    /// Purges what the retention no longer keeps: items past the age limit,
+    /// then the oldest ones until the size limit holds.
+    pub fn expire(&mut self, fs: &dyn FileSystem) -> PurgeResult {
+        let Retention {
+            max_age_days,
+            max_size,
+        } = self.manifest.retention;
+        let now = now_millis();
+        let mut items: Vec<&QuarantineItem> = self.manifest.items.iter().collect();
+        items.sort_by_key(|item| item.quarantined_at);
+
+        let mut total = self.total_size();
+        let mut doomed = Vec::new();
+        for item in items {
+            let too_old = max_age_days.is_some_and(|days| {
+                now.saturating_sub(item.quarantined_at) > u64::from(days) * DAY_MILLIS
+            });
+            let too_big = max_size.is_some_and(|max| total > max);
+            if too_old || too_big {
+                total -= item.entry.size;
+                doomed.push(item.id.clone());
+            }
+        }
+        self.purge_ids(fs, &doomed)
+    }
+
+    fn purge_ids(&mut self, fs: &dyn FileSystem, ids: &[String]) -> PurgeResult {
+        let mut result = PurgeResult::default();
+        let mut kept = Vec::new();
+        for item in std::mem::take(&mut self.manifest.items) {
+            if !ids.contains(&item.id) {
+                kept.push(item);
+                continue;
+            }
+            let stored = PathBuf::from(&item.stored_path);
+            let item_dir = stored.parent().unwrap_or(&stored);
+            match remove_path(fs, item_dir, false).first_failure() {
+                Some(kind) if kind != FailureKind::NotFound => kept.push(item),
+                _ => {
+                    result.freed += item.entry.size;
+                    result.purged.push(item);
+                }
+            }
+        }
+        self.manifest.items = kept;
+        let _ = self.save();
+        result
+    }
+
+    /// The store on the same volume as `path`: the one in the data dir, or
+    /// one at the top of `path`'s volume. Falls back to the data dir, where
+    /// the entry is then copied, when the top of the volume lies inside a
+    /// rule root: the store would be offered for cleaning, and roots such as
+    /// a tmpfs `/tmp` don't survive a reboot.
+    fn store_for(&self, registry: &RuleRegistry, path: &Path) -> PathBuf {
+        let fs = registry.filesystem();
+        let main = self.dir.join("items");
+        let device = |path: &Path| fs.symlink_metadata(path).ok().and_then(|m| m.device);
+        let home_device = self.dir.ancestors().find_map(device);
+        let path_device = device(path);
+        if path_device.is_none() || path_device == home_device {
+            return main;
+        }
+        let volume = volume_store(fs, path, path_device);
+        if registry.roots().iter().any(|root| volume.starts_with(root)) {
+            return main;
+        }
+        match fs.create_dir_all(&volume) {
+            Ok(()) => volume,
+            Err(_) => main,
+        }
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let content = serde_json::to_string_pretty(&self.manifest)?;
+        fs::create_dir_all(&self.dir)?;
+        // Written aside and renamed, so a crash never leaves half a manifest.
+        let staging = self.dir.join(format!("{}.tmp", MANIFEST_FILE));
+        fs::write(&staging, content)?;
+        fs::rename(&staging, self.dir.join(MANIFEST_FILE))
+    }
+}
+
+impl Manifest {
+    fn total_size(&self) -> u64 {
+        self.items.iter().map(|item| item.entry.size).sum()
+    }
+}
+
+fn read_manifest(dir: &Path) -> Result<Manifest, String> {
+    let path = dir.join(MANIFEST_FILE);
+    if !path.is_file() {
+        return Ok(Manifest::default());
+    }
+    let content =
+        fs::read_to_string(&path).map_err(|e| format!("Erro ao ler {}: {}", path.display(), e))?;
+    serde_json::from_str(&content)
+        .map_err(|e| format!("Quarentena inválida em {}: {}", path.display(), e))
+}
+
+fn list(manifest: &Manifest) -> QuarantineList {
+    let mut items = manifest.items.clone();
+    items.sort_by_key(|item| Reverse(item.quarantined_at));
+    QuarantineList {
+        total_size: manifest.total_size(),
+        items,
+        retention: manifest.retention,
+    }
+}
+
+/// The quarantine folder at the top of the volume holding `path`, one per
+/// user on unix.
+fn volume_store(fs: &dyn FileSystem, path: &Path, device: Option<u64>) -> PathBuf {
+    let top = path
+        .ancestors()
+        .skip(1)
+        .take_while(|dir| {
+            fs.symlink_metadata(dir)
+                .ok()
+                .is_some_and(|m| m.device == device)
+        })
+        .last()
+        .unwrap_or(path);
+    if cfg!(unix) {
+        top.join(format!("{}-{}", VOLUME_DIR, current_uid()))
+    } else {
+        top.join(VOLUME_DIR)
+    }
+}
+
+#[cfg(unix)]
+fn current_uid() -> u32 {
+    unsafe { libc::geteuid() }
+}
+
+#[cfg(not(unix))]
+fn current_uid() -> u32 {
+    0
+}
+
+/// Everything at `path`, itself included.
+fn measure(fs: &dyn FileSystem, path: &Path) -> Removed {
+    let mut removed = Removed::default();
+    match fs.symlink_metadata(path).map(|m| (m.kind, m.len)) {
+        Ok((FileKind::Link, _)) => removed.links = 1,
+        Ok((FileKind::Dir, _)) => {
+            let stats = Walker::new(&ScanControl::default()).filesystem(fs).walk(path);
+            removed.files = stats.files;
+            removed.dirs = stats.dirs + 1;
+            removed.links = stats.links;
+            removed.bytes = stats.bytes;
+        }
+        Ok((_, len)) => {
+            removed.files = 1;
+            removed.bytes = len;
+        }
+        Err(_) => {}
+    }
+    removed
+}
+
+/// Renames `from` to `to`, or copies it and then removes it when they are on
+/// different volumes. A failed copy is cleaned up and leaves `from` alone;
+/// once the copy is complete, whatever of `from` can't be removed is
+/// reported in the returned [`Removed`].
+fn move_path(fs: &dyn FileSystem, from: &Path, to: &Path) -> io::Result<Removed> {
+    let contents = measure(fs, from);
+    match fs.rename(from, to) {
+        Ok(()) => Ok(contents),
+        Err(error) if error.kind() == io::ErrorKind::CrossesDevices => {
+            if let Err(error) = copy_tree(fs, from, to) {
+                remove_path(fs, to, false);
+                return Err(error);
+            }
+            Ok(remove_path(fs, from, false))
+        }
+        Err(error) => Err(error),
+    }
+}
+
+/// Copies `from` to `to` without following symlinks. Pipes, sockets and
+/// devices hold no data of their own and are left out: reading a pipe would
+/// block until something writes to it.
+fn copy_tree(fs: &dyn FileSystem, from: &Path, to: &Path) -> io::Result<()> {
+    let metadata = fs.symlink_metadata(from)?;
+    match metadata.kind {
+        FileKind::Link => fs.copy_link(from, to),
+        FileKind::Dir => {
+            fs.create_dir_all(to)?;
+            for entry in fs.read_dir(from)? {
+                copy_tree(fs, &entry.path, &to.join(entry.path.file_name().unwrap_or_default()))?;
+            }
+            fs.copy_permissions(from, to)
+        }
+        FileKind::File => fs.copy_file(from, to),
+        FileKind::Other => Ok(()),
+    }
+}
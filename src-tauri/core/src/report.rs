@@ -0,0 +1,461 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use crate::delete::FailureKind;
+use crate::filters::now_millis;
+use crate::i18n::Catalog;
+use crate::session::{RejectedPath, Rejection};
+use crate::{DeleteReport, DiskInfo, ItemReport, Outcome, ScanResult, SystemInfo, TempFile};
+
+/// Entries listed under "largest" in a report.
+const LARGEST_ENTRIES: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    /// The whole [`Report`].
+    Json,
+    /// One row per entry; totals and host info are left to JSON and HTML.
+    Csv,
+    /// A single page with inline styles, readable offline.
+    Html,
+}
+
+/// What `export_scan_report` writes about: a scan as returned by
+/// `scan_temp_files`, or a cleaning run as returned by `delete_temp_files`.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "kind", content = "result", rename_all = "lowercase")]
+pub enum ReportSource {
+    Scan(ScanResult),
+    Cleaning(DeleteReport),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportKind {
+    Scan,
+    Cleaning,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CategoryTotal {
+    pub category_id: String,
+    pub category: String,
+    pub count: usize,
+    pub size: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub kind: ReportKind,
+    /// Milliseconds since the Unix epoch, like every timestamp here.
+    pub generated_at: u64,
+    /// When the scan or the cleaning run finished, if known.
+    pub finished_at: Option<u64>,
+    pub system: SystemInfo,
+    pub disks: Vec<DiskInfo>,
+    /// Found bytes for a scan, freed bytes for a cleaning run, partial
+    /// deletions included.
+    pub total_size: u64,
+    pub cancelled: bool,
+    /// The cleaning run moved entries into the quarantine.
+    pub quarantined: bool,
+    /// Largest first.
+    pub categories: Vec<CategoryTotal>,
+    pub largest: Vec<TempFile>,
+    /// Found entries for a scan, deleted or quarantined ones for a cleaning
+    /// run.
+    pub entries: Vec<TempFile>,
+    pub rejected: Vec<RejectedPath>,
+    /// Paths of a cleaning run that were failed or only partly deleted.
+    pub failed: Vec<ItemReport>,
+}
+
+impl Report {
+    pub fn new(source: ReportSource, system: SystemInfo, disks: Vec<DiskInfo>) -> Self {
+        let quarantined = matches!(&source, ReportSource::Cleaning(run) if run.quarantined);
+        let (kind, finished_at, total_size, cancelled, entries, rejected, failed) = match source {
+            ReportSource::Scan(scan) => (
+                ReportKind::Scan,
+                scan.scanned_at,
+                scan.total_size,
+                scan.cancelled,
+                scan.files,
+                Vec::new(),
+                Vec::new(),
+            ),
+            ReportSource::Cleaning(run) => (
+                ReportKind::Cleaning,
+                run.finished_at,
+                run.freed_bytes,
+                run.cancelled,
+                run.deleted,
+                run.rejected,
+                run.items
+                    .into_iter()
+                    .filter(|item| item.outcome.is_incomplete())
+                    .collect(),
+            ),
+        };
+
+        let mut categories: Vec<CategoryTotal> = Vec::new();
+        for entry in &entries {
+            match categories
+                .iter_mut()
+                .find(|c| c.category_id == entry.category_id)
+            {
+                Some(total) => {
+                    total.count += 1;
+                    total.size += entry.size;
+                }
+                None => categories.push(CategoryTotal {
+                    category_id: entry.category_id.clone(),
+                    category: entry.category.clone(),
+                    count: 1,
+                    size: entry.size,
+                }),
+            }
+        }
+        categories.sort_by_key(|c| Reverse(c.size));
+
+        let mut largest = entries.clone();
+        largest.sort_by_key(|e| Reverse(e.size));
+        largest.truncate(LARGEST_ENTRIES);
+
+        Report {
+            kind,
+            generated_at: now_millis(),
+            finished_at,
+            system,
+            disks,
+            total_size,
+            cancelled,
+            quarantined,
+            categories,
+            largest,
+            entries,
+            rejected,
+            failed,
+        }
+    }
+
+    pub fn write(&self, path: &Path, format: ReportFormat, catalog: &Catalog) -> Result<(), String> {
+        let content = match format {
+            ReportFormat::Json => serde_json::to_string_pretty(self).map_err(|e| e.to_string())?,
+            ReportFormat::Csv => self.to_csv(),
+            ReportFormat::Html => self.to_html(catalog),
+        };
+        fs::write(path, content)
+            .map_err(|e| format!("Erro ao salvar relatório em {}: {}", path.display(), e))
+    }
+
+    fn to_csv(&self) -> String {
+        let status = match self.kind {
+            ReportKind::Scan => "found",
+            ReportKind::Cleaning if self.quarantined => "quarantined",
+            ReportKind::Cleaning => "deleted",
+        };
+        let mut csv = String::from("status,path,category_id,category,kind,size,modified,reason\r\n");
+        for entry in &self.entries {
+            let kind = serde_json::to_value(entry.kind).unwrap_or_default();
+            let row = [
+                status,
+                &entry.path,
+                &entry.category_id,
+                &entry.category,
+                kind.as_str().unwrap_or_default(),
+                &entry.size.to_string(),
+                &entry.modified.map(|m| m.to_string()).unwrap_or_default(),
+                "",
+            ];
+            push_csv_row(&mut csv, &row);
+        }
+        for rejected in &self.rejected {
+            let row = ["rejected", &rejected.path, "", "", "", "", "", rejection_name(rejected.reason)];
+            push_csv_row(&mut csv, &row);
+        }
+        for item in &self.failed {
+            let reason = item.error.map(failure_name).unwrap_or_default();
+            let row = [outcome_name(item.outcome), &item.path, "", "", "", "", "", reason];
+            push_csv_row(&mut csv, &row);
+        }
+        csv
+    }
+
+    fn to_html(&self, catalog: &Catalog) -> String {
+        let t = |key: &str| escape_html(&catalog.message(key));
+        let title = t(match self.kind {
+            ReportKind::Scan => "report-scan-title",
+            ReportKind::Cleaning if self.quarantined => "report-quarantine-title",
+            ReportKind::Cleaning => "report-cleaning-title",
+        });
+
+        let mut html = String::new();
+        let _ = write!(
+            html,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n\
+             <style>\n\
+             body {{ font-family: system-ui, sans-serif; margin: 2rem; color: #1f2937; }}\n\
+             h1 {{ margin-bottom: 0.25rem; }}\n\
+             table {{ border-collapse: collapse; margin: 0.5rem 0 1.5rem; width: 100%; }}\n\
+             th, td {{ border-bottom: 1px solid #e5e7eb; padding: 0.3rem 0.6rem; text-align: left; }}\n\
+             td.num {{ text-align: right; white-space: nowrap; }}\n\
+             td.path {{ font-family: monospace; word-break: break-all; }}\n\
+             .note {{ color: #b45309; }}\n\
+             </style>\n</head>\n<body>\n<h1>{title}</h1>\n"
+        );
+
+        let _ = writeln!(
+            html,
+            "<p>{}: {}<br>{}: {}<br>{}: {}</p>",
+            t("report-generated-at"),
+            format_timestamp(self.generated_at),
+            t("report-finished-at"),
+            self.finished_at
+                .map(format_timestamp)
+                .unwrap_or_else(|| t("unknown")),
+            t("report-total"),
+            format_size(self.total_size),
+        );
+        if self.cancelled {
+            let note = match self.kind {
+                ReportKind::Scan => "report-cancelled",
+                ReportKind::Cleaning => "report-cleaning-cancelled",
+            };
+            let _ = writeln!(html, "<p class=\"note\">{}</p>", t(note));
+        }
+
+        let _ = writeln!(html, "<h2>{}</h2>\n<table>", t("report-host"));
+        let host = [
+            ("report-os", format!("{} {}", self.system.os_name, self.system.os_version)),
+            ("report-kernel", self.system.kernel_version.clone()),
+            ("report-cpu", format!("{} ({})", self.system.cpu_name, self.system.cpu_cores)),
+            (
+                "report-memory",
+                format!(
+                    "{} / {}",
+                    format_size(self.system.used_memory),
+                    format_size(self.system.total_memory)
+                ),
+            ),
+        ];
+        for (key, value) in host {
+            let _ = writeln!(html, "<tr><th>{}</th><td>{}</td></tr>", t(key), escape_html(&value));
+        }
+        html.push_str("</table>\n");
+
+        let _ = writeln!(
+            html,
+            "<h2>{}</h2>\n<table>\n<tr><th>{}</th><th>{}</th><th>{}</th><th>{}</th><th>{}</th></tr>",
+            t("report-disks"),
+            t("report-disk"),
+            t("report-mount-point"),
+            t("report-file-system"),
+            t("report-free"),
+            t("report-capacity"),
+        );
+        for disk in &self.disks {
+            let _ = writeln!(
+                html,
+                "<tr><td>{}</td><td class=\"path\">{}</td><td>{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td></tr>",
+                escape_html(&disk.name),
+                escape_html(&disk.mount_point),
+                escape_html(&disk.file_system),
+                format_size(disk.available_space),
+                format_size(disk.total_space),
+            );
+        }
+        html.push_str("</table>\n");
+
+        let _ = writeln!(
+            html,
+            "<h2>{}</h2>\n<table>\n<tr><th>{}</th><th>{}</th><th>{}</th></tr>",
+            t("report-categories"),
+            t("report-category"),
+            t("report-entries"),
+            t("report-size"),
+        );
+        for category in &self.categories {
+            let _ = writeln!(
+                html,
+                "<tr><td>{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td></tr>",
+                escape_html(&category.category),
+                category.count,
+                format_size(category.size),
+            );
+        }
+        html.push_str("</table>\n");
+
+        let _ = writeln!(
+            html,
+            "<h2>{}</h2>\n<table>\n<tr><th>{}</th><th>{}</th><th>{}</th><th>{}</th></tr>",
+            t("report-largest"),
+            t("report-path"),
+            t("report-category"),
+            t("report-modified"),
+            t("report-size"),
+        );
+        for entry in &self.largest {
+            let _ = writeln!(
+                html,
+                "<tr><td class=\"path\">{}</td><td>{}</td><td>{}</td><td class=\"num\">{}</td></tr>",
+                escape_html(&entry.path),
+                escape_html(&entry.category),
+                entry.modified.map(format_timestamp).unwrap_or_default(),
+                format_size(entry.size),
+            );
+        }
+        html.push_str("</table>\n");
+
+        if !self.rejected.is_empty() {
+            let _ = writeln!(
+                html,
+                "<h2>{}</h2>\n<table>\n<tr><th>{}</th><th>{}</th></tr>",
+                t("report-rejected"),
+                t("report-path"),
+                t("report-reason"),
+            );
+            for rejected in &self.rejected {
+                let _ = writeln!(
+                    html,
+                    "<tr><td class=\"path\">{}</td><td>{}</td></tr>",
+                    escape_html(&rejected.path),
+                    rejection_name(rejected.reason),
+                );
+            }
+            html.push_str("</table>\n");
+        }
+
+        if !self.failed.is_empty() {
+            let _ = writeln!(
+                html,
+                "<h2>{}</h2>\n<table>\n<tr><th>{}</th><th>{}</th><th>{}</th></tr>",
+                t("report-failed"),
+                t("report-path"),
+                t("report-reason"),
+                t("report-freed"),
+            );
+            for item in &self.failed {
+                let _ = writeln!(
+                    html,
+                    "<tr><td class=\"path\">{}</td><td>{}</td><td class=\"num\">{}</td></tr>",
+                    escape_html(&item.path),
+                    item.error.map(failure_name).unwrap_or_default(),
+                    format_size(item.bytes_freed),
+                );
+            }
+            html.push_str("</table>\n");
+        }
+
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+}
+
+pub fn rejection_name(reason: Rejection) -> &'static str {
+    match reason {
+        Rejection::NotScanned => "not_scanned",
+        Rejection::UnknownRoot => "unknown_root",
+        Rejection::OutsideRoot => "outside_root",
+        Rejection::Missing => "missing",
+        Rejection::Changed => "changed",
+        Rejection::Filtered => "filtered",
+        Rejection::InUse => "in_use",
+        Rejection::Excluded => "excluded",
+    }
+}
+
+pub fn outcome_name(outcome: Outcome) -> &'static str {
+    match outcome {
+        Outcome::Deleted => "deleted",
+        Outcome::PartiallyDeleted => "partially_deleted",
+        Outcome::Skipped => "skipped",
+        Outcome::Failed => "failed",
+    }
+}
+
+pub fn failure_name(kind: FailureKind) -> &'static str {
+    match kind {
+        FailureKind::PermissionDenied => "permission_denied",
+        FailureKind::ReadOnly => "read_only",
+        FailureKind::InUse => "in_use",
+        FailureKind::NotFound => "not_found",
+        FailureKind::Excluded => "excluded",
+        FailureKind::MountPoint => "mount_point",
+        FailureKind::Other => "other",
+    }
+}
+
+fn push_csv_row(csv: &mut String, fields: &[&str]) {
+    for (index, field) in fields.iter().enumerate() {
+        if index > 0 {
+            csv.push(',');
+        }
+        if field.contains([',', '"', '\n', '\r']) {
+            csv.push('"');
+            csv.push_str(&field.replace('"', "\"\""));
+            csv.push('"');
+        } else {
+            csv.push_str(field);
+        }
+    }
+    csv.push_str("\r\n");
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// `YYYY-MM-DD HH:MM:SS UTC` for milliseconds since the Unix epoch.
+fn format_timestamp(millis: u64) -> String {
+    let secs = millis / 1000;
+    let (days, rest) = (secs / 86_400, secs % 86_400);
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm).
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        rest / 3_600,
+        rest % 3_600 / 60,
+        rest % 60
+    )
+}
@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::rules::{expand_path_with, Platform};
+
+/// Where the variables of path templates (`%LOCALAPPDATA%`, `$HOME`, ...)
+/// come from.
+pub trait EnvProvider: Send + Sync {
+    fn var(&self, name: &str) -> Option<String>;
+}
+
+/// The environment of this process.
+pub struct ProcessEnv;
+
+impl EnvProvider for ProcessEnv {
+    fn var(&self, name: &str) -> Option<String> {
+        std::env::var(name).ok()
+    }
+}
+
+/// A fixed set of variables, e.g. the profile of a user on another
+/// machine's disk.
+impl EnvProvider for HashMap<String, String> {
+    fn var(&self, name: &str) -> Option<String> {
+        self.get(name).cloned()
+    }
+}
+
+/// Turns rule path templates into paths on the machine being cleaned. By
+/// default that is this machine: variables come from the process and the
+/// rules of the current OS apply. With a prefix every resolved path is moved
+/// below it, so `C:\Windows\Temp` with prefix `/mnt/win` is looked up as
+/// `/mnt/win/Windows/Temp`, which lets a fixture tree or a mounted volume
+/// stand in for another machine.
+#[derive(Clone)]
+pub struct RootResolver {
+    env: Arc<dyn EnvProvider>,
+    prefix: Option<PathBuf>,
+    platform: Option<Platform>,
+}
+
+impl Default for RootResolver {
+    fn default() -> Self {
+        RootResolver {
+            env: Arc::new(ProcessEnv),
+            prefix: None,
+            platform: Platform::current(),
+        }
+    }
+}
+
+impl fmt::Debug for RootResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RootResolver")
+            .field("prefix", &self.prefix)
+            .field("platform", &self.platform)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RootResolver {
+    /// Resolves against `env` instead of the process environment.
+    pub fn new(env: impl EnvProvider + 'static) -> Self {
+        RootResolver {
+            env: Arc::new(env),
+            ..RootResolver::default()
+        }
+    }
+
+    /// Resolves every path below `prefix`.
+    pub fn prefix(mut self, prefix: impl Into<PathBuf>) -> Self {
+        self.prefix = Some(prefix.into());
+        self
+    }
+
+    /// The OS whose rules apply; the current one by default.
+    pub fn platform(mut self, platform: Platform) -> Self {
+        self.platform = Some(platform);
+        self
+    }
+
+    pub fn target_platform(&self) -> Option<Platform> {
+        self.platform
+    }
+
+    pub fn var(&self, name: &str) -> Option<String> {
+        self.env.var(name)
+    }
+
+    /// Expands `template` as described in [`expand_path_with`]. `None` when
+    /// a variable is unset, or when the path would climb out of the prefix.
+    pub fn resolve(&self, template: &str) -> Option<PathBuf> {
+        let expanded = expand_path_with(template, |name| self.env.var(name))?;
+        match &self.prefix {
+            Some(prefix) => rebase(prefix, &expanded.to_string_lossy()),
+            None => Some(expanded),
+        }
+    }
+}
+
+/// `path`, in either separator style and with or without a drive letter,
+/// as a path below `prefix`.
+fn rebase(prefix: &Path, path: &str) -> Option<PathBuf> {
+    let mut rebased = prefix.to_path_buf();
+    for (index, part) in path.split(['/', '\\']).enumerate() {
+        match part {
+            "" | "." => {}
+            ".." => return None,
+            drive if index == 0 && is_drive(drive) => {}
+            _ => rebased.push(part),
+        }
+    }
+    Some(rebased)
+}
+
+fn is_drive(part: &str) -> bool {
+    let bytes = part.as_bytes();
+    bytes.len() == 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':'
+}
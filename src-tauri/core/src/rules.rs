@@ -0,0 +1,649 @@
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::browsers;
+use crate::exclusions::Exclusions;
+use crate::filesystem::{DirEntry, FileKind, FileSystem, OsFs};
+use crate::filters::{now_millis, EntryFilters};
+use crate::i18n::Catalog;
+use crate::roots::RootResolver;
+use crate::scan::ScanControl;
+use crate::walk::{Walker, DEFAULT_MAX_DEPTH};
+use crate::{is_elevated, EntryKind, TempFile};
+
+const BUILTIN_RULES: [&str; 3] = [
+    include_str!("../rules/windows.toml"),
+    include_str!("../rules/linux.toml"),
+    include_str!("../rules/dev.toml"),
+];
+
+/// File names looked up in the app config dir for user-supplied rules.
+const USER_RULE_FILES: [&str; 2] = ["rules.toml", "rules.json"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Platform {
+    Windows,
+    Linux,
+    Macos,
+}
+
+impl Platform {
+    pub fn current() -> Option<Platform> {
+        match std::env::consts::OS {
+            "windows" => Some(Platform::Windows),
+            "linux" => Some(Platform::Linux),
+            "macos" => Some(Platform::Macos),
+            _ => None,
+        }
+    }
+}
+
+/// Groups of rules that are scanned together.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleFamily {
+    /// OS temp folders, logs and application caches.
+    #[default]
+    System,
+    /// Caches of discovered browser profiles.
+    Browser,
+    /// Package manager and toolchain caches; only scanned on request.
+    Developer,
+}
+
+impl RuleFamily {
+    /// What a scan covers when the request doesn't say.
+    pub const DEFAULT_SCAN: [RuleFamily; 2] = [RuleFamily::System, RuleFamily::Browser];
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleFilters {
+    /// Only report files with one of these extensions (case-insensitive).
+    /// Directories are not affected. Empty means every file.
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    /// Age, size and `include`/`exclude` globs, e.g. `recently-used.xbel.*`.
+    #[serde(flatten)]
+    pub entry: EntryFilters,
+    /// Only report entries owned by the current user. Used for shared
+    /// roots such as `/tmp`; ignored on Windows.
+    #[serde(default)]
+    pub owned_by_user: bool,
+    /// Skip entries whose size is zero.
+    #[serde(default = "default_true")]
+    pub skip_empty: bool,
+}
+
+impl Default for RuleFilters {
+    fn default() -> Self {
+        RuleFilters {
+            extensions: Vec::new(),
+            entry: EntryFilters::default(),
+            owned_by_user: false,
+            skip_empty: true,
+        }
+    }
+}
+
+/// A single place the scanner looks for disposable files.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CleaningRule {
+    pub id: String,
+    /// Root path template, see [`expand_path_with`].
+    pub path: String,
+    /// Stable category id, e.g. `chrome-cache`. Display names come from the
+    /// message catalog.
+    pub category: String,
+    /// Display name for categories the catalog doesn't know.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Browser profile the rule belongs to, shown next to the category name
+    /// so each profile gets its own entry.
+    #[serde(default)]
+    pub profile: Option<String>,
+    #[serde(default)]
+    pub family: RuleFamily,
+    /// Restricts the rule to one OS. `None` applies everywhere.
+    #[serde(default)]
+    pub platform: Option<Platform>,
+    /// When false only the files directly under the root are listed;
+    /// otherwise directories are reported too, sized recursively.
+    #[serde(default = "default_true")]
+    pub recursive: bool,
+    /// How deep below each top-level entry directories are walked.
+    #[serde(default = "default_max_depth")]
+    pub max_depth: usize,
+    /// Stay on the filesystem of the root while scanning and deleting.
+    #[serde(default = "default_true")]
+    pub one_file_system: bool,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Only scanned when the app runs elevated (system package caches).
+    #[serde(default)]
+    pub requires_admin: bool,
+    #[serde(default)]
+    pub filters: RuleFilters,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_max_depth() -> usize {
+    DEFAULT_MAX_DEPTH
+}
+
+impl CleaningRule {
+    /// A rule with the same defaults as one read from a rule file.
+    pub fn new(id: String, path: String, category: &str) -> Self {
+        CleaningRule {
+            id,
+            path,
+            category: category.to_string(),
+            name: None,
+            profile: None,
+            family: RuleFamily::default(),
+            platform: None,
+            recursive: true,
+            max_depth: DEFAULT_MAX_DEPTH,
+            one_file_system: true,
+            enabled: true,
+            requires_admin: false,
+            filters: RuleFilters::default(),
+        }
+    }
+
+    /// Expands the path template with `resolver`.
+    pub fn resolve_root(&self, resolver: &RootResolver) -> Option<PathBuf> {
+        resolver.resolve(&self.path)
+    }
+
+    pub fn category_name(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.category)
+    }
+
+    /// Whether `file` passes this rule's filters and every `extra` layer.
+    pub fn accepts(&self, extra: &[&EntryFilters], file: &TempFile) -> bool {
+        let now = now_millis();
+        std::iter::once(&self.filters.entry)
+            .chain(extra.iter().copied())
+            .all(|filters| filters.matches(file, now))
+    }
+
+    /// Lists the entries under `root` that this rule reports, applying the
+    /// rule's filters plus the `extra` layers. Directories are sized in
+    /// parallel.
+    pub fn scan_root(
+        &self,
+        fs: &dyn FileSystem,
+        root: &Path,
+        extra: &[&EntryFilters],
+        control: &ScanControl,
+    ) -> Vec<TempFile> {
+        let root_path = root.to_string_lossy().to_string();
+        // Followed, as a root may link to another volume.
+        let root_device = fs
+            .metadata(root)
+            .ok()
+            .and_then(|metadata| metadata.device)
+            .filter(|_| self.one_file_system);
+
+        let entries = match fs.read_dir(root) {
+            Ok(entries) => entries,
+            Err(_) => return Vec::new(),
+        };
+
+        entries
+            .par_iter()
+            .filter_map(|entry| self.scan_entry(fs, entry, &root_path, root_device, extra, control))
+            .collect()
+    }
+
+    fn scan_entry(
+        &self,
+        fs: &dyn FileSystem,
+        entry: &DirEntry,
+        root_path: &str,
+        root_device: Option<u64>,
+        extra: &[&EntryFilters],
+        control: &ScanControl,
+    ) -> Option<TempFile> {
+        if control.is_cancelled() {
+            return None;
+        }
+
+        let metadata = &entry.metadata;
+        let path = &entry.path;
+        let name = entry.name.clone();
+
+        // Name globs first, so excluded directories are never walked.
+        let names_match = std::iter::once(&self.filters.entry)
+            .chain(extra.iter().copied())
+            .all(|filters| filters.matches_name(&name, path));
+        if !names_match {
+            return None;
+        }
+        if self.filters.owned_by_user && !metadata.is_owned_by_user() {
+            return None;
+        }
+
+        let modified = metadata.modified;
+        let (kind, size, modified) = match metadata.kind {
+            FileKind::Link => {
+                control.record_entry(0);
+                (EntryKind::Link, metadata.len, modified)
+            }
+            FileKind::File => {
+                if !self.matches_extension(path) {
+                    return None;
+                }
+                control.record_entry(metadata.len);
+                (EntryKind::File, metadata.len, modified)
+            }
+            FileKind::Dir if self.recursive => {
+                if root_device.is_some() && metadata.device != root_device {
+                    return None;
+                }
+                control.record_entry(0);
+                let stats = Walker::new(control)
+                    .filesystem(fs)
+                    .max_depth(self.max_depth)
+                    .one_file_system(self.one_file_system)
+                    .walk(path);
+                (EntryKind::Dir, stats.bytes, modified.max(stats.last_modified))
+            }
+            _ => return None,
+        };
+
+        if size == 0 && self.filters.skip_empty {
+            return None;
+        }
+
+        let file = TempFile {
+            path: path.to_string_lossy().to_string(),
+            name,
+            size,
+            kind,
+            category_id: self.category.clone(),
+            category: self.category_name().to_string(),
+            root_path: root_path.to_string(),
+            modified,
+            accessed: metadata.accessed,
+            created: metadata.created,
+            identity: Some(metadata.identity),
+            in_use: Vec::new(),
+        };
+
+        self.accepts(extra, &file).then_some(file)
+    }
+
+    fn matches_extension(&self, path: &Path) -> bool {
+        if self.filters.extensions.is_empty() {
+            return true;
+        }
+        let ext = match path.extension() {
+            Some(ext) => ext.to_string_lossy(),
+            None => return false,
+        };
+        self.filters
+            .extensions
+            .iter()
+            .any(|wanted| wanted.trim_start_matches('.').eq_ignore_ascii_case(&ext))
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RuleFile {
+    #[serde(default)]
+    pub rules: Vec<CleaningRule>,
+    /// Defaults applied on top of every rule, e.g. a team-wide minimum age.
+    #[serde(default)]
+    pub filters: Option<EntryFilters>,
+}
+
+/// Built-in rules merged with the ones the user dropped in the config dir.
+#[derive(Debug, Clone)]
+pub struct RuleRegistry {
+    rules: Vec<CleaningRule>,
+    /// Rules of the families left out of this scan. Not scanned, but their
+    /// roots are still left out of the rules around them.
+    set_aside: Vec<CleaningRule>,
+    defaults: EntryFilters,
+    exclusions: Exclusions,
+    resolver: RootResolver,
+    fs: Arc<dyn FileSystem>,
+}
+
+impl Default for RuleRegistry {
+    fn default() -> Self {
+        RuleRegistry {
+            rules: Vec::new(),
+            set_aside: Vec::new(),
+            defaults: EntryFilters::default(),
+            exclusions: Exclusions::default(),
+            resolver: RootResolver::default(),
+            fs: Arc::new(OsFs),
+        }
+    }
+}
+
+impl RuleRegistry {
+    pub fn builtin() -> Self {
+        let mut registry = RuleRegistry::default();
+        for source in BUILTIN_RULES {
+            let file: RuleFile =
+                toml::from_str(source).expect("built-in cleaning rules must be valid TOML");
+            registry.merge(file.rules);
+        }
+        registry
+    }
+
+    /// Built-in rules, the browser profiles found on this machine and then
+    /// `rules.toml`/`rules.json` from `config_dir`, if present, along with
+    /// the user's exclusions.
+    pub fn load(config_dir: Option<&Path>) -> Result<Self, String> {
+        RuleRegistry::load_with(config_dir, RootResolver::default())
+    }
+
+    /// Like [`RuleRegistry::load`], with rule paths, browser profiles and
+    /// exclusions resolved by `resolver`, e.g. below a fixture tree or a
+    /// mounted Windows volume.
+    pub fn load_with(config_dir: Option<&Path>, resolver: RootResolver) -> Result<Self, String> {
+        let mut registry = RuleRegistry::builtin();
+        registry.merge(browsers::discover(&resolver));
+        registry.exclusions = Exclusions::load(config_dir, &resolver)?;
+        registry.resolver = resolver;
+        if let Some(dir) = config_dir {
+            for name in USER_RULE_FILES {
+                let path = dir.join(name);
+                if path.is_file() {
+                    let file = parse_rule_file(&path)?;
+                    if let Some(filters) = file.filters {
+                        registry.defaults = filters;
+                    }
+                    registry.merge(file.rules);
+                }
+            }
+        }
+        Ok(registry)
+    }
+
+    /// Adds rules, replacing any existing rule with the same id.
+    pub fn merge(&mut self, rules: Vec<CleaningRule>) {
+        for rule in rules {
+            match self.rules.iter_mut().find(|r| r.id == rule.id) {
+                Some(existing) => *existing = rule,
+                None => self.rules.push(rule),
+            }
+        }
+    }
+
+    pub fn rules(&self) -> &[CleaningRule] {
+        &self.rules
+    }
+
+    /// Names the categories of rules without an explicit `name` from
+    /// `catalog`, followed by the profile if the rule has one.
+    pub fn localize(&mut self, catalog: &Catalog) {
+        for rule in &mut self.rules {
+            if rule.name.is_none() {
+                let name = catalog
+                    .category(&rule.category)
+                    .map(|c| c.name.clone())
+                    .unwrap_or_else(|| rule.category.clone());
+                rule.name = Some(match &rule.profile {
+                    Some(profile) => format!("{} ({})", name, profile),
+                    None => name,
+                });
+            }
+        }
+    }
+
+    /// Sets aside the rules outside `families`, so `~/.cache/pip` is neither
+    /// scanned nor reported as part of `~/.cache` unless asked for.
+    pub fn retain_families(&mut self, families: &[RuleFamily]) {
+        let (kept, set_aside) = std::mem::take(&mut self.rules)
+            .into_iter()
+            .partition(|rule| families.contains(&rule.family));
+        self.rules = kept;
+        self.set_aside.extend(set_aside);
+    }
+
+    /// Drops the rules whose category is not in `categories`.
+    pub fn retain_categories(&mut self, categories: &[String]) {
+        self.rules.retain(|rule| categories.contains(&rule.category));
+    }
+
+    /// Filters from the user rule file that apply to every rule.
+    pub fn defaults(&self) -> &EntryFilters {
+        &self.defaults
+    }
+
+    /// Paths and globs the user protected from cleaning.
+    pub fn exclusions(&self) -> &Exclusions {
+        &self.exclusions
+    }
+
+    /// Scans and cleans through `fs` instead of the local disk.
+    pub fn with_filesystem(mut self, fs: Arc<dyn FileSystem>) -> Self {
+        self.fs = fs;
+        self
+    }
+
+    pub fn resolver(&self) -> &RootResolver {
+        &self.resolver
+    }
+
+    pub fn filesystem(&self) -> &dyn FileSystem {
+        self.fs.as_ref()
+    }
+
+    /// Resolved roots of every rule, also the ones that don't apply here.
+    pub fn roots(&self) -> Vec<PathBuf> {
+        self.rules
+            .iter()
+            .chain(&self.set_aside)
+            .filter_map(|rule| rule.resolve_root(&self.resolver))
+            .collect()
+    }
+
+    /// Enabled rules that apply to the target OS and the privileges we are
+    /// running with.
+    pub fn applicable(&self) -> impl Iterator<Item = &CleaningRule> {
+        let current = self.resolver.target_platform();
+        let elevated = is_elevated();
+        self.rules.iter().filter(move |r| {
+            r.enabled
+                && (r.platform.is_none() || r.platform == current)
+                && (!r.requires_admin || elevated)
+        })
+    }
+
+    /// The applicable rule whose root is exactly `root`.
+    pub fn rule_for_root(&self, root: &Path) -> Option<&CleaningRule> {
+        self.applicable()
+            .find(|rule| rule.resolve_root(&self.resolver).is_some_and(|r| r == root))
+    }
+
+    /// Scans every applicable rule whose root exists, handing each rule's
+    /// entries to `on_rule` as soon as it is done. Stops early once `control`
+    /// is cancelled and returns what was found up to that point.
+    ///
+    /// An entry that contains the root of another rule is left to that rule,
+    /// so `~/.cache/thumbnails` is not counted again as part of `~/.cache`.
+    /// The same goes for the roots of rules set aside by
+    /// [`RuleRegistry::retain_families`].
+    /// Entries the user excluded are skipped.
+    pub fn scan<F>(
+        &self,
+        request: &EntryFilters,
+        control: &ScanControl,
+        mut on_rule: F,
+    ) -> Vec<TempFile>
+    where
+        F: FnMut(&CleaningRule, &[TempFile]),
+    {
+        let roots: Vec<(&CleaningRule, PathBuf)> = self
+            .applicable()
+            .filter_map(|rule| rule.resolve_root(&self.resolver).map(|root| (rule, root)))
+            .filter(|(_, root)| self.fs.metadata(root).is_ok())
+            .collect();
+        let current = self.resolver.target_platform();
+        let set_aside: Vec<PathBuf> = self
+            .set_aside
+            .iter()
+            .filter(|rule| rule.enabled && (rule.platform.is_none() || rule.platform == current))
+            .filter_map(|rule| rule.resolve_root(&self.resolver))
+            .collect();
+        control.set_rules_total(roots.len());
+
+        let mut files = Vec::new();
+        for (rule, root) in &roots {
+            if control.is_cancelled() {
+                break;
+            }
+            control.begin_rule(rule);
+
+            let found: Vec<TempFile> = rule
+                .scan_root(self.filesystem(), root, &[&self.defaults, request], control)
+                .into_iter()
+                .filter(|file| {
+                    let entry = Path::new(&file.path);
+                    !roots.iter().any(|(other, other_root)| {
+                        other.id != rule.id && other_root.starts_with(entry)
+                    }) && !set_aside.iter().any(|root| root.starts_with(entry))
+                        && !self.exclusions.excludes(
+                        entry,
+                        file.kind == EntryKind::Dir,
+                        &file.category_id,
+                    )
+                })
+                .collect();
+
+            control.finish_rule();
+            on_rule(rule, &found);
+            files.extend(found);
+        }
+        files
+    }
+}
+
+pub fn parse_rule_file(path: &Path) -> Result<RuleFile, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Erro ao ler {}: {}", path.display(), e))?;
+
+    let is_json = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+
+    let file: RuleFile = if is_json {
+        serde_json::from_str(&content)
+            .map_err(|e| format!("Regras inválidas em {}: {}", path.display(), e))?
+    } else {
+        toml::from_str(&content)
+            .map_err(|e| format!("Regras inválidas em {}: {}", path.display(), e))?
+    };
+
+    Ok(file)
+}
+
+pub fn expand_path(template: &str) -> Option<PathBuf> {
+    expand_path_with(template, |name| std::env::var(name).ok())
+}
+
+/// Expands `%VAR%`, `${VAR}`, `$VAR` and a leading `~` using `lookup`.
+/// `${VAR:-fallback}` uses `fallback` (itself expanded) when `VAR` is unset,
+/// which covers the XDG base directories. Returns `None` when a referenced
+/// variable is unset or empty, so rules for locations that don't exist on
+/// this machine are simply skipped. An unset `$VAR` past the start is kept
+/// as written instead, as Windows names like `C:\$WINDOWS.~BT` use `$`.
+pub fn expand_path_with<F>(template: &str, lookup: F) -> Option<PathBuf>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    expand(template, &lookup).map(PathBuf::from)
+}
+
+fn expand(template: &str, lookup: &dyn Fn(&str) -> Option<String>) -> Option<String> {
+    let var = |name: &str| lookup(name).filter(|value| !value.is_empty());
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+
+    if let Some(after) = rest.strip_prefix('~') {
+        if after.is_empty() || after.starts_with(['/', '\\']) {
+            out.push_str(&var("HOME").or_else(|| var("USERPROFILE"))?);
+            rest = after;
+        }
+    }
+
+    while let Some(pos) = rest.find(['%', '$']) {
+        out.push_str(&rest[..pos]);
+        let marker = rest.as_bytes()[pos];
+        let after = &rest[pos + 1..];
+
+        if marker == b'%' {
+            match after.find('%') {
+                Some(end) if is_var_name(&after[..end]) => {
+                    out.push_str(&var(&after[..end])?);
+                    rest = &after[end + 1..];
+                }
+                _ => {
+                    out.push('%');
+                    rest = after;
+                }
+            }
+        } else if let Some(braced) = after.strip_prefix('{') {
+            let end = match braced.find('}') {
+                Some(end) => end,
+                None => {
+                    out.push('$');
+                    rest = after;
+                    continue;
+                }
+            };
+            let (name, fallback) = match braced[..end].split_once(":-") {
+                Some((name, fallback)) => (name, Some(fallback)),
+                None => (&braced[..end], None),
+            };
+            if !is_var_name(name) {
+                out.push('$');
+                rest = after;
+                continue;
+            }
+            match (var(name), fallback) {
+                (Some(value), _) => out.push_str(&value),
+                (None, Some(fallback)) => out.push_str(&expand(fallback, lookup)?),
+                (None, None) => return None,
+            }
+            rest = &braced[end + 1..];
+        } else {
+            let end = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            let name = &after[..end];
+            match var(name) {
+                Some(value) if end > 0 => out.push_str(&value),
+                None if end > 0 && out.is_empty() => return None,
+                _ => {
+                    out.push('$');
+                    out.push_str(name);
+                }
+            }
+            rest = &after[end..];
+        }
+    }
+    out.push_str(rest);
+
+    Some(out)
+}
+
+fn is_var_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '(' || c == ')')
+}
@@ -0,0 +1,190 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::progress::ProgressObserver;
+use crate::rules::CleaningRule;
+
+/// Minimum time between two `scan-progress` events.
+const REPORT_INTERVAL: Duration = Duration::from_millis(150);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScanProgress {
+    pub rule_id: String,
+    pub category_id: String,
+    pub category: String,
+    pub rules_done: usize,
+    pub rules_total: usize,
+    pub entries_visited: u64,
+    pub bytes_counted: u64,
+}
+
+/// Results of one rule, emitted as `scan-partial` as soon as the rule is done.
+#[derive(Debug, Clone, Serialize)]
+pub struct ScanPartial {
+    pub rule_id: String,
+    pub category_id: String,
+    pub category: String,
+    pub files: Vec<crate::TempFile>,
+    pub total_size: u64,
+}
+
+type Reporter = Box<dyn Fn(ScanProgress) + Send + Sync>;
+
+/// Shared state of a running scan: cancellation flag, counters and the
+/// progress reporter. Walkers poll [`ScanControl::is_cancelled`] and bail out
+/// early, returning whatever they have counted so far.
+#[derive(Default)]
+pub struct ScanControl {
+    cancelled: AtomicBool,
+    entries_visited: AtomicU64,
+    bytes_counted: AtomicU64,
+    rules_done: AtomicUsize,
+    rules_total: AtomicUsize,
+    /// Rule id, category id and category name of the rule being scanned.
+    current: Mutex<(String, String, String)>,
+    throttle: Throttle,
+    reporter: Option<Reporter>,
+}
+
+impl ScanControl {
+    pub fn new<F>(reporter: F) -> Self
+    where
+        F: Fn(ScanProgress) + Send + Sync + 'static,
+    {
+        ScanControl {
+            reporter: Some(Box::new(reporter)),
+            ..Default::default()
+        }
+    }
+
+    /// Reports progress to `observer`.
+    pub fn observed(observer: Arc<dyn ProgressObserver>) -> Self {
+        ScanControl::new(move |progress| observer.scan_progress(progress))
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_rules_total(&self, total: usize) {
+        self.rules_total.store(total, Ordering::Relaxed);
+    }
+
+    pub fn begin_rule(&self, rule: &CleaningRule) {
+        self.begin(&rule.id, &rule.category, rule.category_name());
+    }
+
+    /// Like [`ScanControl::begin_rule`] for scans that don't go through
+    /// cleaning rules.
+    pub fn begin(&self, id: &str, category_id: &str, category: &str) {
+        if let Ok(mut current) = self.current.lock() {
+            *current = (id.to_string(), category_id.to_string(), category.to_string());
+        }
+        self.report(true);
+    }
+
+    pub fn finish_rule(&self) {
+        self.rules_done.fetch_add(1, Ordering::Relaxed);
+        self.report(true);
+    }
+
+    /// Counts one visited entry holding `bytes` and reports if it is time to.
+    pub fn record_entry(&self, bytes: u64) {
+        self.entries_visited.fetch_add(1, Ordering::Relaxed);
+        self.bytes_counted.fetch_add(bytes, Ordering::Relaxed);
+        self.report(false);
+    }
+
+    pub fn snapshot(&self) -> ScanProgress {
+        let (rule_id, category_id, category) = self
+            .current
+            .lock()
+            .map(|current| current.clone())
+            .unwrap_or_default();
+
+        ScanProgress {
+            rule_id,
+            category_id,
+            category,
+            rules_done: self.rules_done.load(Ordering::Relaxed),
+            rules_total: self.rules_total.load(Ordering::Relaxed),
+            entries_visited: self.entries_visited.load(Ordering::Relaxed),
+            bytes_counted: self.bytes_counted.load(Ordering::Relaxed),
+        }
+    }
+
+    fn report(&self, force: bool) {
+        if let Some(reporter) = &self.reporter {
+            if self.throttle.ready(force) {
+                reporter(self.snapshot());
+            }
+        }
+    }
+}
+
+/// Rate limit shared by the progress events of every scan.
+#[derive(Default)]
+pub struct Throttle {
+    last: Mutex<Option<Instant>>,
+}
+
+impl Throttle {
+    /// True at most once per [`REPORT_INTERVAL`], or whenever `force` is set.
+    pub fn ready(&self, force: bool) -> bool {
+        match self.last.try_lock() {
+            Ok(mut last) => {
+                let due = last.is_none_or(|at| at.elapsed() >= REPORT_INTERVAL);
+                if force || due {
+                    *last = Some(Instant::now());
+                }
+                force || due
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+/// The scan currently running, so `cancel_scan` can reach it.
+#[derive(Default)]
+pub struct ScanJobs {
+    current: Mutex<Option<Arc<ScanControl>>>,
+}
+
+impl ScanJobs {
+    /// Registers a new scan, cancelling the one still running, if any.
+    pub fn start(&self, control: Arc<ScanControl>) {
+        if let Ok(mut current) = self.current.lock() {
+            if let Some(previous) = current.replace(control) {
+                previous.cancel();
+            }
+        }
+    }
+
+    pub fn finish(&self, control: &Arc<ScanControl>) {
+        if let Ok(mut current) = self.current.lock() {
+            if current.as_ref().is_some_and(|c| Arc::ptr_eq(c, control)) {
+                *current = None;
+            }
+        }
+    }
+
+    /// Returns false when there was no scan to cancel.
+    pub fn cancel(&self) -> bool {
+        match self.current.lock() {
+            Ok(current) => match current.as_ref() {
+                Some(control) => {
+                    control.cancel();
+                    true
+                }
+                None => false,
+            },
+            Err(_) => false,
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::delete::ensure_within_root;
+use crate::filters::EntryFilters;
+use crate::rules::{CleaningRule, RuleRegistry};
+use crate::scan::ScanControl;
+use crate::walk::Walker;
+use crate::{EntryKind, TempFile};
+
+/// How many finished scans of each kind are kept around for deletion.
+const MAX_SESSIONS: usize = 4;
+
+/// What a path looked like when it was scanned. Deletion compares it against
+/// the path's current state, so an entry swapped for another file, directory
+/// or link in the meantime is refused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileIdentity {
+    kind: EntryKind,
+    id: (u64, u64),
+}
+
+impl FileIdentity {
+    /// For [`crate::filesystem::FileSystem`] implementations that are not
+    /// backed by the local disk; `id` must change when the entry is replaced.
+    pub fn new(kind: EntryKind, id: (u64, u64)) -> FileIdentity {
+        FileIdentity { kind, id }
+    }
+
+    pub fn of(metadata: &fs::Metadata) -> FileIdentity {
+        let kind = if metadata.file_type().is_symlink() {
+            EntryKind::Link
+        } else if metadata.is_dir() {
+            EntryKind::Dir
+        } else {
+            EntryKind::File
+        };
+        FileIdentity {
+            kind,
+            id: platform_id(metadata),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn platform_id(metadata: &fs::Metadata) -> (u64, u64) {
+    use std::os::unix::fs::MetadataExt;
+    (metadata.dev(), metadata.ino())
+}
+
+/// The file index is not exposed by std on Windows; the creation time is
+/// stable for an entry and changes when it is replaced.
+#[cfg(windows)]
+fn platform_id(metadata: &fs::Metadata) -> (u64, u64) {
+    use std::os::windows::fs::MetadataExt;
+    (metadata.creation_time(), 0)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn platform_id(_metadata: &fs::Metadata) -> (u64, u64) {
+    (0, 0)
+}
+
+/// Why `delete_temp_files` refused a path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rejection {
+    /// The path is not part of the scan session.
+    NotScanned,
+    /// The entry's root no longer belongs to any active cleaning rule.
+    UnknownRoot,
+    /// The path resolves to somewhere outside its root.
+    OutsideRoot,
+    /// The path no longer exists.
+    Missing,
+    /// The path now points at a different file, directory or link.
+    Changed,
+    /// The entry no longer passes the age, size or name filters.
+    Filtered,
+    /// A running process has the entry, or something inside it, open. Pass
+    /// `include_in_use` to delete it anyway.
+    InUse,
+    /// The path, or something inside it, matches one of the user's
+    /// exclusions.
+    Excluded,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RejectedPath {
+    pub path: String,
+    pub reason: Rejection,
+}
+
+/// Entries reported by one scan, keyed by path.
+#[derive(Debug, Default)]
+pub struct ScanSession {
+    entries: HashMap<PathBuf, TempFile>,
+    /// Rules for roots that aren't in the registry, such as the projects
+    /// found by `find_build_artifacts`, keyed by root.
+    rules: HashMap<PathBuf, CleaningRule>,
+}
+
+impl ScanSession {
+    pub fn new(files: &[TempFile]) -> Self {
+        ScanSession {
+            entries: files
+                .iter()
+                .map(|file| (PathBuf::from(&file.path), file.clone()))
+                .collect(),
+            rules: HashMap::new(),
+        }
+    }
+
+    /// A session whose entries are checked against `rules` rather than the
+    /// registry.
+    pub fn with_rules(files: &[TempFile], rules: Vec<CleaningRule>) -> Self {
+        ScanSession {
+            rules: rules
+                .into_iter()
+                .map(|rule| (PathBuf::from(&rule.path), rule))
+                .collect(),
+            ..ScanSession::new(files)
+        }
+    }
+
+    /// The entry at `path` as it was scanned.
+    pub fn entry(&self, path: &Path) -> Option<&TempFile> {
+        self.entries.get(path)
+    }
+
+    /// Checks that `path` may be deleted. Returns the entry with its size
+    /// and timestamps re-read from disk, and the rule it came from. The
+    /// rule, team-default and `request` filters are checked again against
+    /// that fresh state.
+    pub fn validate<'a>(
+        &'a self,
+        registry: &'a RuleRegistry,
+        path: &Path,
+        request: &EntryFilters,
+        control: &ScanControl,
+    ) -> Result<(TempFile, &'a CleaningRule), Rejection> {
+        let entry = self.entries.get(path).ok_or(Rejection::NotScanned)?;
+        if registry
+            .exclusions()
+            .excludes(path, entry.kind == EntryKind::Dir, &entry.category_id) {
+            return Err(Rejection::Excluded);
+        }
+        let root = PathBuf::from(&entry.root_path);
+        let rule = self
+            .rules
+            .get(&root)
+            .or_else(|| registry.rule_for_root(&root))
+            .ok_or(Rejection::UnknownRoot)?;
+
+        let fs = registry.filesystem();
+        ensure_within_root(fs, path, &root).map_err(|_| Rejection::OutsideRoot)?;
+
+        let metadata = fs.symlink_metadata(path).map_err(|_| Rejection::Missing)?;
+        if entry.identity != Some(metadata.identity) {
+            return Err(Rejection::Changed);
+        }
+
+        let modified = metadata.modified;
+        let (size, modified) = match entry.kind {
+            EntryKind::Dir => {
+                let stats = Walker::new(control)
+                    .filesystem(fs)
+                    .max_depth(rule.max_depth)
+                    .one_file_system(rule.one_file_system)
+                    .walk(path);
+                (stats.bytes, modified.max(stats.last_modified))
+            }
+            _ => (metadata.len, modified),
+        };
+        let current = TempFile {
+            size,
+            modified,
+            accessed: metadata.accessed,
+            created: metadata.created,
+            ..entry.clone()
+        };
+
+        if !rule.accepts(&[registry.defaults(), request], &current) {
+            return Err(Rejection::Filtered);
+        }
+
+        Ok((current, rule))
+    }
+}
+
+/// Recent sessions of one kind of scan, most recent last.
+pub struct Sessions<T> {
+    sessions: Mutex<Vec<(String, Arc<T>)>>,
+}
+
+pub type ScanSessions = Sessions<ScanSession>;
+
+impl<T> Default for Sessions<T> {
+    fn default() -> Self {
+        Sessions {
+            sessions: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl<T> Sessions<T> {
+    /// Stores the result of a scan and returns its session id.
+    pub fn insert(&self, session: T) -> String {
+        let id = uuid::Uuid::new_v4().to_string();
+        if let Ok(mut sessions) = self.sessions.lock() {
+            sessions.push((id.clone(), Arc::new(session)));
+            let excess = sessions.len().saturating_sub(MAX_SESSIONS);
+            sessions.drain(..excess);
+        }
+        id
+    }
+
+    pub fn get(&self, id: &str) -> Option<Arc<T>> {
+        self.sessions.lock().ok().and_then(|sessions| {
+            sessions
+                .iter()
+                .find(|(session_id, _)| session_id == id)
+                .map(|(_, session)| session.clone())
+        })
+    }
+}
@@ -0,0 +1,249 @@
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::scan::ScanControl;
+use crate::walk::{device_of, DEFAULT_MAX_DEPTH};
+
+/// Largest files remembered per directory; the rest only count towards
+/// the directory's totals and its "other" bucket.
+const KEPT_FILES: usize = 64;
+
+/// How many analysed trees are kept for drilling down.
+const MAX_TREES: usize = 4;
+
+fn default_depth() -> usize {
+    2
+}
+
+fn default_top_n() -> usize {
+    20
+}
+
+/// What `analyze_path` reports.
+#[derive(Debug, Clone, Deserialize)]
+pub struct UsageQuery {
+    /// Any directory, e.g. a `DiskInfo.mount_point`.
+    pub path: String,
+    /// Levels of children to include below `path`.
+    #[serde(default = "default_depth")]
+    pub depth: usize,
+    /// Children kept per node; the rest are summed into one `other` node.
+    #[serde(default = "default_top_n")]
+    pub top_n: usize,
+    /// Walk the disk again instead of reusing an earlier analysis.
+    #[serde(default)]
+    pub refresh: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UsageKind {
+    Dir,
+    File,
+    /// Everything that didn't make the top-N, summed up.
+    Other,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageNode {
+    pub name: String,
+    /// Empty for `other` nodes.
+    pub path: String,
+    pub kind: UsageKind,
+    pub size: u64,
+    /// Files and directories below the node, itself excluded.
+    pub files: u64,
+    pub dirs: u64,
+    /// Largest first.
+    pub children: Vec<UsageNode>,
+    /// True when the node has children left out by `depth`; call
+    /// `analyze_path` on it to drill down.
+    pub truncated: bool,
+}
+
+/// Sizes of one directory and everything below it. Symlinks are not
+/// followed and the walk stays on the filesystem it started on.
+#[derive(Debug, Default)]
+pub struct DirTree {
+    name: String,
+    bytes: u64,
+    files: u64,
+    dirs: u64,
+    subdirs: Vec<DirTree>,
+    /// Largest files directly inside, biggest first.
+    largest: Vec<(String, u64)>,
+    /// Bytes and count of the files directly inside not in `largest`.
+    rest: (u64, u64),
+}
+
+impl DirTree {
+    pub fn build(path: &Path, control: &ScanControl) -> DirTree {
+        let device = fs::symlink_metadata(path).ok().and_then(|m| device_of(&m));
+        DirTree::walk(path, 0, device, control)
+    }
+
+    fn walk(path: &Path, depth: usize, device: Option<u64>, control: &ScanControl) -> DirTree {
+        let mut tree = DirTree {
+            name: path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            ..DirTree::default()
+        };
+        let entries = match fs::read_dir(path) {
+            Ok(entries) => entries,
+            Err(_) => return tree,
+        };
+
+        let mut subdirs = Vec::new();
+        let mut files = Vec::new();
+        for entry in entries.flatten() {
+            if control.is_cancelled() {
+                break;
+            }
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+            if metadata.is_dir() && !metadata.file_type().is_symlink() {
+                control.record_entry(0);
+                tree.dirs += 1;
+                if depth < DEFAULT_MAX_DEPTH && device_of(&metadata) == device {
+                    subdirs.push(entry.path());
+                }
+            } else {
+                let size = if metadata.is_file() { metadata.len() } else { 0 };
+                control.record_entry(size);
+                tree.files += 1;
+                tree.bytes += size;
+                files.push((entry.file_name().to_string_lossy().to_string(), size));
+            }
+        }
+
+        files.sort_by_key(|(_, size)| Reverse(*size));
+        let rest = files.split_off(files.len().min(KEPT_FILES));
+        tree.rest = (rest.iter().map(|(_, size)| size).sum(), rest.len() as u64);
+        tree.largest = files;
+
+        tree.subdirs = subdirs
+            .par_iter()
+            .map(|subdir| DirTree::walk(subdir, depth + 1, device, control))
+            .collect();
+        for subdir in &tree.subdirs {
+            tree.bytes += subdir.bytes;
+            tree.files += subdir.files;
+            tree.dirs += subdir.dirs;
+        }
+        tree
+    }
+
+    /// The subtree at `relative`, a path below this tree's root.
+    fn descend(&self, relative: &Path) -> Option<&DirTree> {
+        relative
+            .components()
+            .try_fold(self, |tree, component| match component {
+                Component::Normal(name) => tree.subdirs.iter().find(|d| name == d.name.as_str()),
+                _ => None,
+            })
+    }
+
+    /// A node for this directory, located at `path`, with `depth` levels of
+    /// children.
+    pub fn node(&self, path: &Path, depth: usize, top_n: usize) -> UsageNode {
+        let mut node = UsageNode {
+            name: path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| path.to_string_lossy().to_string()),
+            path: path.to_string_lossy().to_string(),
+            kind: UsageKind::Dir,
+            size: self.bytes,
+            files: self.files,
+            dirs: self.dirs,
+            children: Vec::new(),
+            truncated: false,
+        };
+        if self.subdirs.is_empty() && self.largest.is_empty() {
+            return node;
+        }
+        if depth == 0 {
+            node.truncated = true;
+            return node;
+        }
+
+        let mut children: Vec<UsageNode> = self
+            .subdirs
+            .iter()
+            .map(|subdir| subdir.node(&path.join(&subdir.name), depth - 1, top_n))
+            .chain(self.largest.iter().map(|(name, size)| UsageNode {
+                name: name.clone(),
+                path: path.join(name).to_string_lossy().to_string(),
+                kind: UsageKind::File,
+                size: *size,
+                files: 0,
+                dirs: 0,
+                children: Vec::new(),
+                truncated: false,
+            }))
+            .collect();
+        children.sort_by_key(|child| Reverse(child.size));
+
+        let mut other = UsageNode {
+            name: "other".to_string(),
+            path: String::new(),
+            kind: UsageKind::Other,
+            size: self.rest.0,
+            files: self.rest.1,
+            dirs: 0,
+            children: Vec::new(),
+            truncated: false,
+        };
+        for child in children.drain(children.len().min(top_n)..) {
+            other.size += child.size;
+            match child.kind {
+                UsageKind::Dir => {
+                    other.files += child.files;
+                    other.dirs += child.dirs + 1;
+                }
+                _ => other.files += 1,
+            }
+        }
+        if other.files > 0 || other.dirs > 0 {
+            children.push(other);
+        }
+        node.children = children;
+        node
+    }
+}
+
+/// Recently analysed trees, most recent last, so drilling down into a
+/// subfolder doesn't walk the disk again.
+#[derive(Default)]
+pub struct UsageCache {
+    trees: Mutex<Vec<(PathBuf, Arc<DirTree>)>>,
+}
+
+impl UsageCache {
+    pub fn insert(&self, root: PathBuf, tree: Arc<DirTree>) {
+        if let Ok(mut trees) = self.trees.lock() {
+            // A fresh analysis replaces every older one it covers.
+            trees.retain(|(cached, _)| !cached.starts_with(&root));
+            trees.push((root, tree));
+            let excess = trees.len().saturating_sub(MAX_TREES);
+            trees.drain(..excess);
+        }
+    }
+
+    /// Node for `path` from the most recent cached tree covering it.
+    pub fn node(&self, path: &Path, depth: usize, top_n: usize) -> Option<UsageNode> {
+        let trees = self.trees.lock().ok()?;
+        trees.iter().rev().find_map(|(root, tree)| {
+            let subtree = tree.descend(path.strip_prefix(root).ok()?)?;
+            Some(subtree.node(path, depth, top_n))
+        })
+    }
+}
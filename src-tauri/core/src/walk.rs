@@ -0,0 +1,201 @@
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::ops::Add;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::filesystem::{FileKind, FileSystem, Metadata, OsFs};
+use crate::scan::ScanControl;
+
+/// Directories nested deeper than this below the walk root are counted but
+/// not descended into.
+pub const DEFAULT_MAX_DEPTH: usize = 64;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct DirStats {
+    /// Files with several hard links below the root are counted once.
+    pub files: u64,
+    pub dirs: u64,
+    /// Symlinks (and junctions on Windows) found; they are never followed.
+    pub links: u64,
+    pub bytes: u64,
+    /// Newest modification time of anything below the root, in milliseconds
+    /// since the Unix epoch.
+    pub last_modified: Option<u64>,
+}
+
+impl Add for DirStats {
+    type Output = DirStats;
+
+    fn add(self, other: DirStats) -> DirStats {
+        DirStats {
+            files: self.files + other.files,
+            dirs: self.dirs + other.dirs,
+            links: self.links + other.links,
+            bytes: self.bytes + other.bytes,
+            last_modified: self.last_modified.max(other.last_modified),
+        }
+    }
+}
+
+/// Identity of a directory, used to catch loops and directories reachable
+/// through more than one path, or of a hard-linked file.
+#[derive(Debug, Hash, PartialEq, Eq)]
+enum DirKey {
+    Inode(u64, u64),
+    Path(PathBuf),
+}
+
+#[cfg(unix)]
+pub fn device_of(metadata: &fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.dev())
+}
+
+/// Device and inode of a file, shared by all of its hard links.
+#[cfg(unix)]
+pub fn inode_of(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(unix)]
+pub fn hard_links_of(metadata: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.nlink()
+}
+
+/// std doesn't expose the file index on Windows, so hard links can't be told
+/// apart from copies there.
+#[cfg(not(unix))]
+pub fn inode_of(_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+#[cfg(not(unix))]
+pub fn hard_links_of(_metadata: &fs::Metadata) -> u64 {
+    1
+}
+
+/// Windows mount points are junctions, which are treated as links and never
+/// entered, so there is no device to compare.
+#[cfg(not(unix))]
+pub fn device_of(_metadata: &fs::Metadata) -> Option<u64> {
+    None
+}
+
+/// Parallel directory walker. Subdirectories are handed to rayon's
+/// work-stealing pool, so one huge cache folder keeps every core busy.
+/// Symlinks are counted as links and never followed.
+pub struct Walker<'a> {
+    control: &'a ScanControl,
+    fs: &'a dyn FileSystem,
+    max_depth: usize,
+    one_file_system: bool,
+    visited: Mutex<HashSet<DirKey>>,
+}
+
+impl<'a> Walker<'a> {
+    pub fn new(control: &'a ScanControl) -> Self {
+        Walker {
+            control,
+            fs: &OsFs,
+            max_depth: DEFAULT_MAX_DEPTH,
+            one_file_system: false,
+            visited: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Walks `fs` instead of the local disk.
+    pub fn filesystem(mut self, fs: &'a dyn FileSystem) -> Self {
+        self.fs = fs;
+        self
+    }
+
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Don't descend into directories on another device than the walk root.
+    pub fn one_file_system(mut self, one_file_system: bool) -> Self {
+        self.one_file_system = one_file_system;
+        self
+    }
+
+    /// Counts everything below `path`, not including `path` itself. A `path`
+    /// that is a symlink is not entered.
+    pub fn walk(&self, path: &Path) -> DirStats {
+        match self.fs.symlink_metadata(path) {
+            Ok(metadata) if metadata.is_dir() && self.first_visit(path, &metadata) => {
+                let device = metadata.device.filter(|_| self.one_file_system);
+                self.walk_dir(path, 0, device)
+            }
+            _ => DirStats::default(),
+        }
+    }
+
+    fn walk_dir(&self, path: &Path, depth: usize, device: Option<u64>) -> DirStats {
+        let entries = match self.fs.read_dir(path) {
+            Ok(entries) => entries,
+            Err(_) => return DirStats::default(),
+        };
+
+        let mut stats = DirStats::default();
+        let mut subdirs: Vec<PathBuf> = Vec::new();
+
+        for entry in entries {
+            if self.control.is_cancelled() {
+                break;
+            }
+            let metadata = entry.metadata;
+            stats.last_modified = stats.last_modified.max(metadata.modified);
+
+            match metadata.kind {
+                FileKind::Link => {
+                    stats.links += 1;
+                    self.control.record_entry(0);
+                }
+                FileKind::File => {
+                    if metadata.hard_links > 1 && !self.first_visit(&entry.path, &metadata) {
+                        continue;
+                    }
+                    stats.files += 1;
+                    stats.bytes += metadata.len;
+                    self.control.record_entry(metadata.len);
+                }
+                FileKind::Dir => {
+                    stats.dirs += 1;
+                    self.control.record_entry(0);
+                    let same_device = device.is_none() || metadata.device == device;
+                    if depth < self.max_depth
+                        && same_device
+                        && self.first_visit(&entry.path, &metadata)
+                    {
+                        subdirs.push(entry.path);
+                    }
+                }
+                FileKind::Other => {}
+            }
+        }
+
+        subdirs
+            .par_iter()
+            .map(|subdir| self.walk_dir(subdir, depth + 1, device))
+            .reduce(DirStats::default, Add::add)
+            + stats
+    }
+
+    fn first_visit(&self, path: &Path, metadata: &Metadata) -> bool {
+        let key = match metadata.inode {
+            Some((device, inode)) => Some(DirKey::Inode(device, inode)),
+            None => self.fs.canonicalize(path).ok().map(DirKey::Path),
+        };
+        match (key, self.visited.lock()) {
+            (Some(key), Ok(mut visited)) => visited.insert(key),
+            _ => true,
+        }
+    }
+}
@@ -0,0 +1,135 @@
+//! Searches a fixture folder of projects for build output.
+
+mod common;
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use common::Fixture;
+use zcleaner_core::artifacts::{self, ArtifactFinder, ArtifactSort, ProjectInfo};
+use zcleaner_core::i18n::{Catalog, Locale};
+use zcleaner_core::scan::ScanControl;
+use zcleaner_core::TempFile;
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Fixtures here are a folder of projects.
+impl Fixture {
+    /// A file of `size` bytes last modified `age` ago.
+    fn aged(&self, relative: &str, size: usize, age: Duration) -> PathBuf {
+        let path = self.file(relative, size);
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(SystemTime::now() - age)
+            .unwrap();
+        path
+    }
+
+    fn find(&self, stale_days: Option<u32>) -> (Vec<TempFile>, Vec<ProjectInfo>) {
+        let control = ScanControl::default();
+        ArtifactFinder::new(&control, Catalog::get(Locale::En))
+            .stale_days(stale_days)
+            .find(&self.dir, &[])
+    }
+
+    fn relative(&self, path: &str) -> String {
+        Path::new(path)
+            .strip_prefix(&self.dir)
+            .unwrap()
+            .to_string_lossy()
+            .replace('\\', "/")
+    }
+}
+
+#[test]
+fn build_output_needs_its_project_marker() {
+    let code = Fixture::new("artifacts", "markers");
+    let now = Duration::ZERO;
+    code.aged("rusty/Cargo.toml", 10, now);
+    code.aged("rusty/target/debug/app", 300, now);
+    code.aged("loose/target/out.bin", 100, now);
+    code.aged("web/package.json", 10, now);
+    code.aged("web/node_modules/dep/index.js", 200, now);
+    code.aged("web/node_modules/dep/node_modules/sub/index.js", 50, now);
+    code.aged("app/build/classes/A.class", 100, now);
+    code.aged("py/pyproject.toml", 10, now);
+    code.aged("py/pkg/__pycache__/m.cpython-312.pyc", 20, now);
+    code.aged("py/.venv/pyvenv.cfg", 10, now);
+    code.aged("py/.venv/lib/site.py", 40, now);
+    code.aged("notes/.venv/lib/site.py", 40, now);
+
+    let (files, projects) = code.find(None);
+    let mut found: Vec<(String, &str, String, u64)> = files
+        .iter()
+        .map(|f| {
+            let root = code.relative(&f.root_path);
+            (code.relative(&f.path), f.category_id.as_str(), root, f.size)
+        })
+        .collect();
+    found.sort();
+    // Nested output is part of its parent; `target`, `build` and `.venv`
+    // without their marker are left alone; `__pycache__` belongs to the
+    // Python project above it.
+    assert_eq!(
+        found,
+        [
+            ("py/.venv".to_string(), "python-venv", "py".to_string(), 50),
+            ("py/pkg/__pycache__".to_string(), "python-bytecode", "py".to_string(), 20),
+            ("rusty/target".to_string(), "rust-target", "rusty".to_string(), 300),
+            ("web/node_modules".to_string(), "node-modules", "web".to_string(), 250),
+        ]
+    );
+    let mut sizes: Vec<(String, u64)> = projects
+        .iter()
+        .map(|p| (code.relative(&p.path), p.artifact_size))
+        .collect();
+    sizes.sort();
+    assert_eq!(
+        sizes,
+        [
+            ("py".to_string(), 70),
+            ("rusty".to_string(), 300),
+            ("web".to_string(), 250),
+        ]
+    );
+}
+
+#[test]
+fn projects_are_ranked_by_their_own_files_only() {
+    let code = Fixture::new("artifacts", "staleness");
+    // The build output is recent everywhere; only the sources tell how
+    // long a project has been left alone.
+    code.aged("old/Cargo.toml", 10, DAY * 400);
+    code.aged("old/src/main.rs", 10, DAY * 300);
+    code.aged("old/target/debug/app", 100, Duration::ZERO);
+    code.aged("older/package.json", 10, DAY * 500);
+    code.aged("older/node_modules/dep/index.js", 50, Duration::ZERO);
+    code.aged("fresh/Cargo.toml", 10, DAY);
+    code.aged("fresh/target/debug/app", 200, Duration::ZERO);
+
+    let (mut files, mut projects) = code.find(None);
+    artifacts::sort(&mut files, &mut projects, ArtifactSort::Staleness);
+    let order: Vec<String> = projects.iter().map(|p| code.relative(&p.path)).collect();
+    assert_eq!(order, ["older", "old", "fresh"]);
+    let roots: Vec<String> = files.iter().map(|f| code.relative(&f.root_path)).collect();
+    assert_eq!(roots, ["older", "old", "fresh"]);
+
+    artifacts::sort(&mut files, &mut projects, ArtifactSort::Size);
+    let order: Vec<String> = projects.iter().map(|p| code.relative(&p.path)).collect();
+    assert_eq!(order, ["fresh", "old", "older"]);
+
+    // Projects touched within the last 30 days are not offered.
+    let (files, projects) = code.find(Some(30));
+    let mut roots: Vec<String> = projects.iter().map(|p| code.relative(&p.path)).collect();
+    roots.sort();
+    assert_eq!(roots, ["old", "older"]);
+    assert_eq!(files.len(), 2);
+    // `old` was last touched by `src/main.rs`, not by its `target`.
+    let old = projects.iter().find(|p| p.path.ends_with("old")).unwrap();
+    let modified = SystemTime::UNIX_EPOCH + Duration::from_millis(old.last_modified.unwrap());
+    let age = SystemTime::now().duration_since(modified).unwrap();
+    assert!(age > DAY * 299 && age < DAY * 301);
+}
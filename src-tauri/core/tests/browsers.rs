@@ -0,0 +1,209 @@
+//! Discovers browser profiles in a fixture home directory laid out like a
+//! Linux user's, and checks the cache rules made for them.
+
+mod common;
+
+use std::collections::HashMap;
+
+use common::Fixture;
+use zcleaner_core::browsers;
+use zcleaner_core::roots::RootResolver;
+use zcleaner_core::rules::Platform;
+
+/// Fixtures here are a fake file system root; `~` is `/home/bob` below it.
+impl Fixture {
+    /// Writes `content` at `relative` to the home directory.
+    fn home_file(&self, relative: &str, content: &str) {
+        self.write(&format!("home/bob/{}", relative), content);
+    }
+
+    fn home_dir(&self, relative: &str) {
+        self.dir(&format!("home/bob/{}", relative));
+    }
+
+    /// `(id, path template, profile)` of the discovered rules of `category`.
+    fn rules(&self, category: &str) -> Vec<(String, String, String)> {
+        let env = HashMap::from([("HOME".to_string(), "/home/bob".to_string())]);
+        let resolver = RootResolver::new(env)
+            .prefix(&self.dir)
+            .platform(Platform::Linux);
+        let mut rules: Vec<(String, String, String)> = browsers::discover(&resolver)
+            .into_iter()
+            .filter(|rule| rule.category == category)
+            .map(|rule| (rule.id, rule.path, rule.profile.unwrap_or_default()))
+            .collect();
+        rules.sort();
+        rules
+    }
+}
+
+fn rule(id: &str, path: &str, profile: &str) -> (String, String, String) {
+    (id.to_string(), path.to_string(), profile.to_string())
+}
+
+const FIREFOX_CACHE: &str = "${XDG_CACHE_HOME:-~/.cache}/mozilla/firefox";
+
+#[test]
+fn firefox_profiles_are_found_with_relative_and_absolute_paths() {
+    let home = Fixture::new("browsers", "firefox");
+    home.home_file(
+        ".mozilla/firefox/profiles.ini",
+        "[General]\nStartWithLastProfile=1\n\n\
+         [Profile1]\nName=Work\nIsRelative=0\nPath=/home/bob/work\n\n\
+         [Profile0]\nName=default-release\nIsRelative=1\nPath=Profiles/ab12.default-release\n\n\
+         [Install4F96D1932A9F858E]\nDefault=Profiles/ab12.default-release\n",
+    );
+    home.home_file(
+        ".mozilla/firefox/installs.ini",
+        "[4F96D1932A9F858E]\nDefault=Profiles/cd34.dev-edition-default\n",
+    );
+
+    let rules = home.rules("firefox-cache");
+    // Six folders per profile; the install default listed in both files
+    // is one profile.
+    assert_eq!(rules.len(), 18);
+    assert!(rules.contains(&rule(
+        "firefox-ab12.default-release-cache2",
+        &format!("{}/Profiles/ab12.default-release/cache2", FIREFOX_CACHE),
+        "default-release",
+    )));
+    assert!(rules.contains(&rule(
+        "firefox-ab12.default-release-shader-cache",
+        "~/.mozilla/firefox/Profiles/ab12.default-release/shader-cache",
+        "default-release",
+    )));
+    // Absolute profiles keep their cache in the profile folder.
+    assert!(rules.contains(&rule("firefox-work-cache2", "/home/bob/work/cache2", "Work")));
+    assert!(rules.contains(&rule(
+        "firefox-work-shader-cache",
+        "/home/bob/work/shader-cache",
+        "Work",
+    )));
+    // Profiles only named in `installs.ini` are named after their folder.
+    assert!(rules.contains(&rule(
+        "firefox-cd34.dev-edition-default-startupcache",
+        &format!("{}/Profiles/cd34.dev-edition-default/startupCache", FIREFOX_CACHE),
+        "cd34.dev-edition-default",
+    )));
+}
+
+#[test]
+fn firefox_profiles_of_the_same_name_keep_their_own_rules() {
+    let home = Fixture::new("browsers", "firefox-same-name");
+    let ini = "[Profile0]\nName=default\nIsRelative=1\nPath=Profiles/ab12.default\n";
+    home.home_file(".mozilla/firefox/profiles.ini", ini);
+    home.home_file(".config/mozilla/firefox/profiles.ini", ini);
+
+    // The cache half is shared, and only the profile folders differ.
+    let rules = home.rules("firefox-cache");
+    assert_eq!(rules.len(), 7);
+    let id_of = |path: &str| {
+        let found = rules.iter().find(|(_, p, _)| p == path);
+        found.map(|(id, _, _)| id.as_str()).unwrap()
+    };
+    assert_eq!(
+        id_of("~/.mozilla/firefox/Profiles/ab12.default/shader-cache"),
+        "firefox-ab12.default-shader-cache"
+    );
+    let other = id_of(&format!(
+        "{}/Profiles/ab12.default/shader-cache",
+        "${XDG_CONFIG_HOME:-~/.config}/mozilla/firefox"
+    ));
+    assert!(other.starts_with("firefox-ab12.default-") && other.ends_with("-shader-cache"));
+    assert_ne!(other, "firefox-ab12.default-shader-cache");
+}
+
+#[test]
+fn malformed_firefox_profiles_are_skipped() {
+    let home = Fixture::new("browsers", "firefox-malformed");
+    home.home_file(
+        ".mozilla/firefox/profiles.ini",
+        "\u{feff}Path=before-any-section\n\
+         ; a comment\n\
+         [Profile0]\nName=NoPath\n\
+         [Profile1]\nName=Empty\nPath=\n\
+         [Profile2]\nName=Escape\nPath=../../outside\n\
+         [Profile3]\nName=Variable\nPath=$HOME/profile\n\
+         not an entry\n\
+         [Profile4\nName=Unclosed\n\
+         [Profile5]\n  Name = Spaced \n  Path = Profiles/ok.default \n",
+    );
+
+    let profiles: Vec<String> = home
+        .rules("firefox-cache")
+        .into_iter()
+        .map(|(_, _, profile)| profile)
+        .collect();
+    assert_eq!(profiles, ["Spaced"; 6]);
+
+    // Without a readable profiles.ini there is nothing to clean.
+    let home = Fixture::new("browsers", "firefox-missing");
+    home.home_dir(".mozilla/firefox");
+    assert!(home.rules("firefox-cache").is_empty());
+}
+
+const CHROME_CACHE: &str = "${XDG_CACHE_HOME:-~/.cache}/google-chrome";
+
+#[test]
+fn chromium_profiles_come_from_local_state() {
+    let home = Fixture::new("browsers", "chromium");
+    home.home_file(
+        ".config/google-chrome/Local State",
+        r#"{"profile": {"info_cache": {
+            "Default": {"name": "Person 1"},
+            "Profile 3": {"name": "Work"},
+            "Guest Profile": {"name": ""}
+        }}}"#,
+    );
+
+    let rules = home.rules("chrome-cache");
+    assert_eq!(rules.len(), 9);
+    assert!(rules.contains(&rule(
+        "chrome-profile-3-code-cache",
+        &format!("{}/Profile 3/Code Cache", CHROME_CACHE),
+        "Work",
+    )));
+    assert!(rules.contains(&rule(
+        "chrome-profile-3-gpucache",
+        "${XDG_CONFIG_HOME:-~/.config}/google-chrome/Profile 3/GPUCache",
+        "Work",
+    )));
+    assert!(rules.contains(&rule(
+        "chrome-default-cache",
+        &format!("{}/Default/Cache", CHROME_CACHE),
+        "Person 1",
+    )));
+    // A profile without a name goes by its folder.
+    assert!(rules.contains(&rule(
+        "chrome-guest-profile-cache",
+        &format!("{}/Guest Profile/Cache", CHROME_CACHE),
+        "Guest Profile",
+    )));
+}
+
+#[test]
+fn chromium_falls_back_to_the_default_profile() {
+    // No `profile.info_cache`, but a `Default` folder.
+    let home = Fixture::new("browsers", "chromium-no-cache");
+    home.home_file(".config/google-chrome/Local State", r#"{"browser": {}}"#);
+    home.home_dir(".config/google-chrome/Default");
+    let rules = home.rules("chrome-cache");
+    assert_eq!(rules.len(), 3);
+    assert!(rules.iter().all(|(_, _, profile)| profile == "Default"));
+    assert!(rules.contains(&rule(
+        "chrome-default-cache",
+        &format!("{}/Default/Cache", CHROME_CACHE),
+        "Default",
+    )));
+
+    // Unreadable `Local State`, same fallback.
+    let home = Fixture::new("browsers", "chromium-broken");
+    home.home_file(".config/google-chrome/Local State", "{not json");
+    home.home_dir(".config/google-chrome/Default");
+    assert_eq!(home.rules("chrome-cache").len(), 3);
+
+    // Neither: nothing to clean.
+    let home = Fixture::new("browsers", "chromium-empty");
+    home.home_file(".config/google-chrome/Local State", "{}");
+    assert!(home.rules("chrome-cache").is_empty());
+}
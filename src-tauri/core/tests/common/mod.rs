@@ -0,0 +1,58 @@
+//! The throwaway directory trees the integration tests run against.
+
+// Each test crate uses its own subset.
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::PathBuf;
+
+/// A directory under the system temp dir, removed on drop.
+pub struct Fixture {
+    pub dir: PathBuf,
+}
+
+impl Fixture {
+    /// An empty `zcleaner-{prefix}-{name}-{pid}`, so tests running in
+    /// parallel never share one.
+    pub fn new(prefix: &str, name: &str) -> Fixture {
+        let dir = std::env::temp_dir().join(format!(
+            "zcleaner-{}-{}-{}",
+            prefix,
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        Fixture { dir }
+    }
+
+    pub fn path(&self, relative: &str) -> PathBuf {
+        self.dir.join(relative)
+    }
+
+    /// Creates the directory at `relative` and its parents.
+    pub fn dir(&self, relative: &str) -> PathBuf {
+        let path = self.path(relative);
+        fs::create_dir_all(&path).unwrap();
+        path
+    }
+
+    /// Writes `content` at `relative`, creating its parents.
+    pub fn write(&self, relative: &str, content: impl AsRef<[u8]>) -> PathBuf {
+        let path = self.path(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    /// A file of `size` bytes.
+    pub fn file(&self, relative: &str, size: usize) -> PathBuf {
+        self.write(relative, vec![b'x'; size])
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
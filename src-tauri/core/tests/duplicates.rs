@@ -0,0 +1,129 @@
+//! Finds and resolves duplicate files in throwaway directory trees.
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use common::Fixture;
+use zcleaner_core::duplicates::{
+    DuplicateFinder, DuplicateSession, Resolution, ResolveMode, ResolveResult,
+};
+use zcleaner_core::filters::EntryFilters;
+use zcleaner_core::i18n::{Catalog, Locale};
+use zcleaner_core::scan::ScanControl;
+
+impl Fixture {
+    fn find(&self) -> DuplicateSession {
+        let control = ScanControl::default();
+        let filters = EntryFilters::default();
+        DuplicateFinder::new(&control, &filters, &()).find(std::slice::from_ref(&self.dir))
+    }
+}
+
+fn text(path: &Path) -> String {
+    path.to_string_lossy().to_string()
+}
+
+/// Larger than the two blocks of the partial hash, so a change in the
+/// middle only shows in the full hash.
+fn content(middle: u8) -> Vec<u8> {
+    let mut content = vec![b'x'; 10_000];
+    content[5_000] = middle;
+    content
+}
+
+fn resolve(session: &DuplicateSession, keep: &Path, remove: &[&Path]) -> ResolveResult {
+    let resolution = Resolution {
+        keep: text(keep),
+        remove: remove.iter().map(|path| text(path)).collect(),
+    };
+    let mut result = ResolveResult::default();
+    session.resolve(
+        &resolution,
+        ResolveMode::Delete,
+        Catalog::get(Locale::En),
+        &mut result,
+    );
+    result
+}
+
+#[test]
+fn files_are_grouped_by_size_then_content_hash() {
+    let fixture = Fixture::new("duplicates", "group");
+    let a = fixture.write("a.bin", content(b'x'));
+    let b = fixture.write("sub/b.bin", content(b'x'));
+    // Same size and same first and last block, different middle.
+    fixture.write("c.bin", content(b'y'));
+    // Same leading bytes, other size.
+    fixture.write("d.bin", &content(b'x')[..9_000]);
+    fixture.write("empty-1", b"");
+    fixture.write("empty-2", b"");
+
+    let session = fixture.find();
+    assert_eq!(session.sets.len(), 1);
+    let set = &session.sets[0];
+    assert_eq!(set.hash, blake3::hash(&content(b'x')).to_hex().to_string());
+    assert_eq!((set.size, set.wasted), (10_000, 10_000));
+    let mut files: Vec<&str> = set.files.iter().map(|f| f.path.as_str()).collect();
+    files.sort();
+    assert_eq!(files, [text(&a), text(&b)]);
+}
+
+#[test]
+fn hard_links_are_not_duplicates() {
+    let fixture = Fixture::new("duplicates", "hard-links");
+    let a = fixture.write("a.bin", content(b'x'));
+    fs::hard_link(&a, fixture.path("a-link.bin")).unwrap();
+    assert!(fixture.find().sets.is_empty());
+
+    // A real copy makes a set of two, the link still counted once.
+    fixture.write("b.bin", content(b'x'));
+    let session = fixture.find();
+    assert_eq!(session.sets.len(), 1);
+    assert_eq!(session.sets[0].files.len(), 2);
+    assert_eq!(session.sets[0].wasted, 10_000);
+}
+
+#[test]
+fn resolving_keeps_exactly_one_copy() {
+    let fixture = Fixture::new("duplicates", "resolve");
+    let a = fixture.write("a.bin", content(b'x'));
+    let b = fixture.write("b.bin", content(b'x'));
+    let c = fixture.write("c.bin", content(b'x'));
+
+    let session = fixture.find();
+    // Listing the kept file among the ones to remove is refused.
+    let result = resolve(&session, &a, &[&b, &c, &a]);
+    assert_eq!(result.resolved, [text(&b), text(&c)]);
+    assert_eq!(result.freed, 20_000);
+    assert_eq!(result.failed.len(), 1);
+    assert_eq!(result.failed[0].path, text(&a));
+    assert!(a.exists() && !b.exists() && !c.exists());
+    assert_eq!(fs::read(&a).unwrap(), content(b'x'));
+}
+
+#[test]
+fn resolving_refuses_files_changed_after_the_scan() {
+    let fixture = Fixture::new("duplicates", "changed");
+    let a = fixture.write("a.bin", content(b'x'));
+    let b = fixture.write("b.bin", content(b'x'));
+
+    let session = fixture.find();
+    fs::write(&b, content(b'y')).unwrap();
+    let result = resolve(&session, &a, &[&b]);
+    assert!(result.resolved.is_empty());
+    assert_eq!(result.freed, 0);
+    assert_eq!(
+        result.failed[0].reason,
+        Catalog::get(Locale::En).message("duplicate-changed")
+    );
+    assert!(a.exists() && b.exists());
+
+    // A changed kept file protects every copy.
+    fs::write(&b, content(b'x')).unwrap();
+    let session = fixture.find();
+    fs::write(&a, content(b'y')).unwrap();
+    let result = resolve(&session, &a, &[&b]);
+    assert!(result.resolved.is_empty() && b.exists());
+}
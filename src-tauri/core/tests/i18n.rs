@@ -0,0 +1,67 @@
+//! Checks the built-in catalogs against each other and the built-in rules.
+
+use zcleaner_core::i18n::{Catalog, Locale};
+use zcleaner_core::rules::RuleRegistry;
+
+const CATALOGS: [(&str, &str); 3] = [
+    ("pt-BR", include_str!("../locales/pt-BR.toml")),
+    ("en", include_str!("../locales/en.toml")),
+    ("es", include_str!("../locales/es.toml")),
+];
+
+/// Keys of `section`, read straight from the file so the fallback to pt-BR
+/// can't hide a gap.
+fn keys(source: &str, section: &str) -> Vec<String> {
+    let table: toml::Table = toml::from_str(source).unwrap();
+    let mut keys: Vec<String> = table[section].as_table().unwrap().keys().cloned().collect();
+    keys.sort();
+    keys
+}
+
+#[test]
+fn locales_parse_from_their_tags() {
+    let locales: Vec<Locale> = serde_json::from_str(r#"["pt-BR", "en", "es"]"#).unwrap();
+    assert_eq!(locales, [Locale::PtBr, Locale::En, Locale::Es]);
+    assert!(serde_json::from_str::<Locale>(r#""fr""#).is_err());
+    assert!(serde_json::from_str::<Locale>(r#""pt-br""#).is_err());
+    assert_eq!(Locale::default(), Locale::PtBr);
+}
+
+#[test]
+fn missing_text_falls_back_to_pt_br_then_the_key() {
+    let pt_br = Catalog::get(Locale::PtBr);
+    let en = Catalog::get(Locale::En);
+    let empty = Catalog::default();
+
+    assert_ne!(en.message("quarantine-unknown"), pt_br.message("quarantine-unknown"));
+    assert_eq!(empty.message("quarantine-unknown"), pt_br.message("quarantine-unknown"));
+    assert_eq!(empty.message("no-such-key"), "no-such-key");
+    assert_eq!(empty.category("tmp").unwrap().name, pt_br.category("tmp").unwrap().name);
+    assert!(empty.category("no-such-category").is_none());
+
+    let args = [("done", "1".to_string()), ("total", "3".to_string())];
+    assert_eq!(
+        en.format("memory-partial", &args),
+        "Partial optimization: 1 of 3 operations completed"
+    );
+}
+
+#[test]
+fn every_built_in_category_is_named_in_every_catalog() {
+    let registry = RuleRegistry::builtin();
+    let mut categories: Vec<&str> = registry.rules().iter().map(|r| r.category.as_str()).collect();
+    categories.sort();
+    categories.dedup();
+
+    let messages = keys(CATALOGS[0].1, "messages");
+    for (locale, source) in CATALOGS {
+        let named = keys(source, "categories");
+        let missing: Vec<&str> = categories
+            .iter()
+            .copied()
+            .filter(|id| !named.iter().any(|name| name == id))
+            .collect();
+        assert!(missing.is_empty(), "{} has no name for {:?}", locale, missing);
+        assert_eq!(keys(source, "messages"), messages, "messages of {}", locale);
+    }
+}
@@ -0,0 +1,127 @@
+//! Writes scan reports in every format and reads them back.
+
+use std::fs;
+use std::path::PathBuf;
+
+use zcleaner_core::i18n::{Catalog, Locale};
+use zcleaner_core::report::{Report, ReportFormat, ReportSource};
+use zcleaner_core::{DiskInfo, SystemInfo};
+
+/// 2000-02-29 12:34:56 UTC.
+const LEAP_DAY: u64 = 951_827_696_000;
+/// 2024-12-31 23:59:59.999 UTC.
+const NEW_YEARS_EVE: u64 = 1_735_689_599_999;
+
+const AWKWARD_PATH: &str = r#"/tmp/a,b "c"/<x>&y.log"#;
+
+/// A scan of two entries whose paths and names need quoting or escaping.
+fn report() -> Report {
+    let scan = serde_json::json!({
+        "kind": "scan",
+        "result": {
+            "session_id": "s",
+            "total_size": 30,
+            "scanned_at": LEAP_DAY,
+            "files": [
+                {
+                    "path": AWKWARD_PATH,
+                    "name": r#"<x>&y.log"#,
+                    "size": 20,
+                    "kind": "file",
+                    "category_id": "fixture",
+                    "category": r#"Cache, "big""#,
+                    "root_path": "/tmp",
+                    "modified": NEW_YEARS_EVE
+                },
+                {
+                    "path": "/tmp/line\nbreak",
+                    "name": "line\nbreak",
+                    "size": 10,
+                    "kind": "dir",
+                    "category_id": "fixture",
+                    "category": r#"Cache, "big""#,
+                    "root_path": "/tmp",
+                    "modified": 0
+                }
+            ]
+        }
+    });
+    let source: ReportSource = serde_json::from_value(scan).unwrap();
+    let system = SystemInfo {
+        cpu_name: "CPU <1>".to_string(),
+        cpu_cores: 4,
+        total_memory: 1024,
+        used_memory: 512,
+        os_name: "Linux".to_string(),
+        os_version: "6".to_string(),
+        kernel_version: "6.1".to_string(),
+    };
+    let disks = vec![DiskInfo {
+        name: "<disk>".to_string(),
+        mount_point: "/mnt/a&b".to_string(),
+        total_space: 2048,
+        available_space: 1024,
+        is_removable: false,
+        file_system: "ext4".to_string(),
+    }];
+    Report::new(source, system, disks)
+}
+
+fn written(report: &Report, format: ReportFormat, name: &str) -> String {
+    let path: PathBuf =
+        std::env::temp_dir().join(format!("zcleaner-report-{}-{}", name, std::process::id()));
+    report
+        .write(&path, format, Catalog::get(Locale::En))
+        .unwrap();
+    let content = fs::read_to_string(&path).unwrap();
+    let _ = fs::remove_file(&path);
+    content
+}
+
+#[test]
+fn csv_quotes_fields_with_separators_quotes_and_line_breaks() {
+    let csv = written(&report(), ReportFormat::Csv, "csv");
+    let rows: Vec<&str> = csv.split("\r\n").collect();
+    assert_eq!(
+        rows,
+        [
+            "status,path,category_id,category,kind,size,modified,reason",
+            &format!(
+                r#"found,"/tmp/a,b ""c""/<x>&y.log",fixture,"Cache, ""big""",file,20,{},"#,
+                NEW_YEARS_EVE
+            ),
+            "found,\"/tmp/line\nbreak\",fixture,\"Cache, \"\"big\"\"\",dir,10,0,",
+            "",
+        ]
+    );
+}
+
+#[test]
+fn html_escapes_every_value_from_disk() {
+    let html = written(&report(), ReportFormat::Html, "html");
+    assert!(html.contains("/tmp/a,b &quot;c&quot;/&lt;x&gt;&amp;y.log"));
+    assert!(html.contains("Cache, &quot;big&quot;"));
+    assert!(html.contains("&lt;disk&gt;") && html.contains("/mnt/a&amp;b"));
+    assert!(html.contains("CPU &lt;1&gt; (4)"));
+    assert!(!html.contains("<x>") && !html.contains("<disk>") && !html.contains("<1>"));
+}
+
+#[test]
+fn html_timestamps_are_utc_dates() {
+    let html = written(&report(), ReportFormat::Html, "timestamps");
+    // When the scan finished, and when each entry was modified.
+    assert!(html.contains("2000-02-29 12:34:56 UTC"));
+    assert!(html.contains("2024-12-31 23:59:59 UTC"));
+    assert!(html.contains("1970-01-01 00:00:00 UTC"));
+}
+
+#[test]
+fn json_keeps_the_raw_values() {
+    let json: serde_json::Value =
+        serde_json::from_str(&written(&report(), ReportFormat::Json, "json")).unwrap();
+    assert_eq!(json["kind"], "scan");
+    assert_eq!(json["finished_at"], LEAP_DAY);
+    assert_eq!(json["total_size"], 30);
+    assert_eq!(json["largest"][0]["path"], AWKWARD_PATH);
+    assert_eq!(json["categories"][0]["count"], 2);
+}
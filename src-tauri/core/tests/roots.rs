@@ -0,0 +1,367 @@
+//! Runs the built-in Windows and Linux rules against fixture trees laid out
+//! like the system drive or root of those OSes, on whatever OS runs the
+//! tests.
+
+mod common;
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use common::Fixture;
+use zcleaner_core::cleaning::DeleteControl;
+use zcleaner_core::filesystem::{DirEntry, FileSystem, Metadata, OsFs};
+use zcleaner_core::filters::EntryFilters;
+use zcleaner_core::roots::RootResolver;
+use zcleaner_core::rules::{expand_path_with, Platform, RuleFamily, RuleRegistry};
+use zcleaner_core::scan::ScanControl;
+use zcleaner_core::session::ScanSession;
+use zcleaner_core::{delete_entries, is_elevated, scan_entries, TempFile};
+
+const CATEGORIES: [&str; 3] = ["windows-temp", "system-temp", "chrome-cache"];
+
+/// Fixtures here are a fake `C:` drive, or the root of a Linux machine,
+/// with `bob` as the user.
+impl Fixture {
+    fn resolver(&self, platform: Platform) -> RootResolver {
+        let env: &[(&str, &str)] = match platform {
+            Platform::Windows => &[
+                ("TEMP", r"C:\Users\bob\AppData\Local\Temp"),
+                ("LOCALAPPDATA", r"C:\Users\bob\AppData\Local"),
+                ("APPDATA", r"C:\Users\bob\AppData\Roaming"),
+                ("ProgramFiles(x86)", r"C:\Program Files (x86)"),
+            ],
+            _ => &[("HOME", "/home/bob")],
+        };
+        let env: HashMap<String, String> = env
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        RootResolver::new(env).prefix(&self.dir).platform(platform)
+    }
+
+    fn registry(&self, exclusions: &str) -> RuleRegistry {
+        let config = self.dir("config");
+        fs::write(config.join("exclusions.toml"), exclusions).unwrap();
+        let resolver = self.resolver(Platform::Windows);
+        let mut registry = RuleRegistry::load_with(Some(&config), resolver).unwrap();
+        let categories: Vec<String> = CATEGORIES.iter().map(|c| c.to_string()).collect();
+        registry.retain_categories(&categories);
+        registry
+    }
+
+    /// What a default scan of a Linux machine covers.
+    fn linux_registry(&self) -> RuleRegistry {
+        let resolver = self.resolver(Platform::Linux);
+        let mut registry = RuleRegistry::load_with(None, resolver).unwrap();
+        registry.retain_families(&RuleFamily::DEFAULT_SCAN);
+        registry
+    }
+}
+
+/// The local disk, with every removal recorded.
+#[derive(Debug, Default)]
+struct RecordingFs {
+    removed: Mutex<Vec<PathBuf>>,
+}
+
+impl FileSystem for RecordingFs {
+    fn symlink_metadata(&self, path: &Path) -> io::Result<Metadata> {
+        OsFs.symlink_metadata(path)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        OsFs.metadata(path)
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<DirEntry>> {
+        OsFs.read_dir(path)
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        OsFs.canonicalize(path)
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        self.removed.lock().unwrap().push(path.to_path_buf());
+        OsFs.remove_file(path)
+    }
+
+    fn remove_dir(&self, path: &Path) -> io::Result<()> {
+        self.removed.lock().unwrap().push(path.to_path_buf());
+        OsFs.remove_dir(path)
+    }
+}
+
+/// The local disk, with the entries in `foreign` owned by another user.
+#[derive(Debug)]
+struct ForeignFs {
+    foreign: Vec<PathBuf>,
+}
+
+impl ForeignFs {
+    fn owner(&self, path: &Path, mut metadata: Metadata) -> Metadata {
+        if self.foreign.iter().any(|foreign| foreign == path) {
+            metadata.owner = Some(u32::MAX);
+        }
+        metadata
+    }
+}
+
+impl FileSystem for ForeignFs {
+    fn symlink_metadata(&self, path: &Path) -> io::Result<Metadata> {
+        Ok(self.owner(path, OsFs.symlink_metadata(path)?))
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        Ok(self.owner(path, OsFs.metadata(path)?))
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<DirEntry>> {
+        let mut entries = OsFs.read_dir(path)?;
+        for entry in &mut entries {
+            entry.metadata = self.owner(&entry.path, entry.metadata.clone());
+        }
+        Ok(entries)
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        OsFs.canonicalize(path)
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        OsFs.remove_file(path)
+    }
+
+    fn remove_dir(&self, path: &Path) -> io::Result<()> {
+        OsFs.remove_dir(path)
+    }
+}
+
+fn scan(registry: &RuleRegistry) -> Vec<TempFile> {
+    let mut files = scan_entries(
+        registry,
+        &EntryFilters::default(),
+        &ScanControl::default(),
+        &(),
+    );
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    files
+}
+
+#[test]
+fn windows_rules_scan_a_drive_below_the_prefix() {
+    let drive = Fixture::new("drive", "scan");
+    drive.file("Windows/Temp/setup.log", 100);
+    drive.file("Users/bob/AppData/Local/Temp/installer.tmp", 200);
+    drive.file("Users/bob/AppData/Local/Temp/keep/state.db", 50);
+    drive.file(
+        "Users/bob/AppData/Local/Google/Chrome/User Data/Default/Cache/data_0",
+        300,
+    );
+    drive.file("Users/bob/Documents/report.txt", 400);
+
+    let registry = drive.registry("[[exclusions]]\npath = '%TEMP%\\keep'\n");
+    let files = scan(&registry);
+    let found: Vec<(PathBuf, &str, u64)> = files
+        .iter()
+        .map(|f| {
+            let relative = Path::new(&f.path).strip_prefix(&drive.dir).unwrap();
+            (relative.to_path_buf(), f.category_id.as_str(), f.size)
+        })
+        .collect();
+
+    assert_eq!(
+        found,
+        [
+            (
+                PathBuf::from(
+                    "Users/bob/AppData/Local/Google/Chrome/User Data/Default/Cache/data_0"
+                ),
+                "chrome-cache",
+                300
+            ),
+            (
+                PathBuf::from("Users/bob/AppData/Local/Temp/installer.tmp"),
+                "windows-temp",
+                200
+            ),
+            (PathBuf::from("Windows/Temp/setup.log"), "system-temp", 100),
+        ]
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn linux_rules_scan_a_root_below_the_prefix() {
+    let root = Fixture::new("drive", "linux");
+    root.file("home/bob/.cache/app/data.bin", 100);
+    root.file("home/bob/.cache/thumbnails/normal/a.png", 10);
+    // Left to the developer rules, which a default scan doesn't cover.
+    root.file("home/bob/.cache/pip/http/b.whl", 1000);
+    root.file("home/bob/.local/share/Trash/files/old.txt", 20);
+    root.file("home/bob/.local/share/Trash/info/old.txt.trashinfo", 5);
+    root.file("home/bob/.local/share/recently-used.xbel", 8);
+    root.file("home/bob/.local/share/recently-used.xbel.tmp", 7);
+    root.file("home/bob/.local/share/applications/app.desktop", 9);
+    root.file("tmp/mine.tmp", 30);
+    let theirs = root.file("tmp/theirs.tmp", 30);
+    root.file("var/tmp/mine.tmp", 30);
+    root.file("var/cache/apt/archives/a.deb", 40);
+    root.file("var/cache/apt/archives/lock", 1);
+    root.file("var/cache/apt/archives/partial/b.deb", 40);
+    root.file("var/cache/dnf/fedora/packages/c.rpm", 50);
+    root.file("var/cache/pacman/pkg/d.pkg.tar.zst", 60);
+
+    let registry = root
+        .linux_registry()
+        .with_filesystem(Arc::new(ForeignFs { foreign: vec![theirs] }));
+    let files = scan(&registry);
+    let found: Vec<(PathBuf, &str, u64)> = files
+        .iter()
+        .map(|f| {
+            let relative = Path::new(&f.path).strip_prefix(&root.dir).unwrap();
+            (relative.to_path_buf(), f.category_id.as_str(), f.size)
+        })
+        .collect();
+
+    // Package caches are only scanned as root.
+    let expected: Vec<(PathBuf, &str, u64)> = [
+        ("home/bob/.cache/app", "user-cache", 100),
+        ("home/bob/.cache/thumbnails/normal", "thumbnails", 10),
+        ("home/bob/.local/share/Trash/files", "trash", 20),
+        ("home/bob/.local/share/Trash/info", "trash", 5),
+        ("home/bob/.local/share/recently-used.xbel.tmp", "recent-files", 7),
+        ("tmp/mine.tmp", "tmp", 30),
+        ("var/cache/apt/archives/a.deb", "apt-cache", 40),
+        ("var/cache/dnf/fedora", "dnf-cache", 50),
+        ("var/cache/pacman/pkg/d.pkg.tar.zst", "pacman-cache", 60),
+        ("var/tmp/mine.tmp", "var-tmp", 30),
+    ]
+    .into_iter()
+    .filter(|(path, _, _)| is_elevated() || !path.starts_with("var/cache"))
+    .map(|(path, category, size)| (PathBuf::from(path), category, size))
+    .collect();
+    assert_eq!(found, expected);
+}
+
+#[test]
+fn clean_goes_through_the_registry_filesystem() {
+    let drive = Fixture::new("drive", "clean");
+    let log = drive.file("Windows/Temp/setup.log", 100);
+    drive.file("Windows/Temp/cab/a.cab", 100);
+
+    let recorder = Arc::new(RecordingFs::default());
+    let registry = drive.registry("").with_filesystem(recorder.clone());
+    let files = scan(&registry);
+    let paths: Vec<String> = files.iter().map(|f| f.path.clone()).collect();
+    let result = delete_entries(
+        &registry,
+        &ScanSession::new(&files),
+        &paths,
+        &EntryFilters::default(),
+        true,
+        None,
+        &DeleteControl::default(),
+    );
+
+    assert_eq!(result.deleted_size, 200);
+    let mut removed = recorder.removed.lock().unwrap().clone();
+    removed.sort();
+    assert_eq!(
+        removed,
+        [
+            drive.path("Windows/Temp/cab"),
+            drive.path("Windows/Temp/cab/a.cab"),
+            log
+        ]
+    );
+}
+
+#[test]
+fn resolved_paths_never_leave_the_prefix() {
+    let resolver = RootResolver::new(HashMap::from([(
+        "TEMP".to_string(),
+        r"C:\Users\bob\..\..\etc".to_string(),
+    )]))
+    .prefix("/mnt/c");
+
+    assert_eq!(
+        resolver.resolve(r"C:\Windows\Temp"),
+        Some(PathBuf::from("/mnt/c/Windows/Temp"))
+    );
+    assert_eq!(resolver.resolve("%TEMP%"), None);
+    assert_eq!(resolver.resolve(r"%LOCALAPPDATA%\Temp"), None);
+}
+
+#[test]
+fn path_templates_expand_variables_and_fallbacks() {
+    let env = HashMap::from([
+        ("HOME".to_string(), "/home/bob".to_string()),
+        ("XDG_DATA_HOME".to_string(), String::new()),
+        ("ProgramFiles(x86)".to_string(), r"C:\Program Files (x86)".to_string()),
+    ]);
+    let expand = |template: &str| expand_path_with(template, |name| env.get(name).cloned());
+
+    // Unset and empty variables fall back, and the fallback is expanded.
+    assert_eq!(
+        expand("${XDG_CACHE_HOME:-~/.cache}/tool"),
+        Some(PathBuf::from("/home/bob/.cache/tool"))
+    );
+    assert_eq!(
+        expand("${XDG_DATA_HOME:-$HOME/.local/share}"),
+        Some(PathBuf::from("/home/bob/.local/share"))
+    );
+    assert_eq!(
+        expand(r"%ProgramFiles(x86)%\Steam"),
+        Some(PathBuf::from(r"C:\Program Files (x86)\Steam"))
+    );
+
+    // A rule on an unset variable has no root.
+    assert_eq!(expand(r"%PROGRAMDATA%\x"), None);
+    assert_eq!(expand("${CARGO_HOME}/registry"), None);
+    assert_eq!(expand("$CARGO_HOME/registry"), None);
+    assert_eq!(expand("${CARGO_HOME:-$RUSTUP}/registry"), None);
+
+    // `$` and `%` that aren't variables are kept.
+    assert_eq!(
+        expand(r"C:\$WINDOWS.~BT\Sources"),
+        Some(PathBuf::from(r"C:\$WINDOWS.~BT\Sources"))
+    );
+    assert_eq!(
+        expand("~/50% off/$"),
+        Some(PathBuf::from("/home/bob/50% off/$"))
+    );
+}
+
+#[test]
+fn unset_variables_skip_rules_and_literal_dollars_stay_excluded() {
+    let drive = Fixture::new("drive", "templates");
+    drive.file("Program Files (x86)/Steam/logs/a.log", 100);
+    drive.file("Windows/Temp/setup.log", 100);
+    drive.file("Windows/Temp/$WINDOWS.~BT/Sources/b.bin", 100);
+    drive.write(
+        "config/rules.toml",
+        "[[rules]]\nid = \"steam-logs\"\npath = '%ProgramFiles(x86)%\\Steam\\logs'\n\
+         category = \"system-temp\"\n\n\
+         [[rules]]\nid = \"unset\"\npath = '%STEAM_HOME%\\logs'\ncategory = \"system-temp\"\n",
+    );
+
+    let registry = drive.registry("[[exclusions]]\npath = 'C:\\Windows\\Temp\\$WINDOWS.~BT'\n");
+    let unset = registry.rules().iter().find(|rule| rule.id == "unset").unwrap();
+    assert_eq!(unset.resolve_root(registry.resolver()), None);
+
+    let found: Vec<PathBuf> = scan(&registry)
+        .iter()
+        .map(|f| Path::new(&f.path).strip_prefix(&drive.dir).unwrap().to_path_buf())
+        .collect();
+    assert_eq!(
+        found,
+        [
+            PathBuf::from("Program Files (x86)/Steam/logs/a.log"),
+            PathBuf::from("Windows/Temp/setup.log"),
+        ]
+    );
+}
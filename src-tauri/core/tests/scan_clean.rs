@@ -0,0 +1,1015 @@
+//! Scans and cleans throwaway directory trees through the same entry points
+//! the desktop app and the CLI use.
+
+mod common;
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use common::Fixture;
+use zcleaner_core::cleaning::{DeleteControl, DeleteJob, DeleteJobs, JobState};
+use zcleaner_core::delete::{ensure_within_root, plan_removal, remove_path, FailureKind};
+use zcleaner_core::filesystem::{DirEntry, FileSystem, Metadata, OsFs};
+use zcleaner_core::filters::{EntryFilters, NamePattern};
+use zcleaner_core::i18n::{Catalog, Locale};
+use zcleaner_core::quarantine::{Quarantine, Retention};
+use zcleaner_core::rules::{CleaningRule, RuleFamily, RuleRegistry};
+use zcleaner_core::scan::{ScanControl, ScanPartial};
+use zcleaner_core::session::{Rejection, ScanSession};
+use zcleaner_core::{
+    delete_entries, plan_entries, scan_entries, DeleteProgress, EntryKind, Outcome,
+    ProgressObserver, TempFile,
+};
+
+const CATEGORIES: [&str; 2] = ["fixture-cache", "fixture-logs"];
+
+/// The config dir of a fixture holds the rule and exclusion files the
+/// registry loads.
+impl Fixture {
+    /// A rule for each `(category, relative root)`, plus `exclusions` as the
+    /// body of `exclusions.toml`.
+    fn registry(&self, rules: &[(&str, &str)], exclusions: &str) -> RuleRegistry {
+        let mut toml = String::new();
+        for (index, (category, root)) in rules.iter().enumerate() {
+            toml.push_str(&format!(
+                "[[rules]]\nid = \"fixture-{}\"\npath = '{}'\ncategory = \"{}\"\n\n",
+                index,
+                self.path(root).display(),
+                category
+            ));
+        }
+        let config = self.dir("config");
+        fs::write(config.join("rules.toml"), toml).unwrap();
+        fs::write(config.join("exclusions.toml"), exclusions).unwrap();
+
+        let mut registry = RuleRegistry::load(Some(&config)).unwrap();
+        let categories: Vec<String> = CATEGORIES.iter().map(|c| c.to_string()).collect();
+        registry.retain_categories(&categories);
+        registry
+    }
+}
+
+#[derive(Default)]
+struct Recorder {
+    partials: Mutex<Vec<ScanPartial>>,
+    deletes: Mutex<Vec<DeleteProgress>>,
+}
+
+impl ProgressObserver for Recorder {
+    fn scan_partial(&self, partial: ScanPartial) {
+        self.partials.lock().unwrap().push(partial);
+    }
+
+    fn delete_progress(&self, progress: DeleteProgress) {
+        self.deletes.lock().unwrap().push(progress);
+    }
+}
+
+/// The local disk, where removing anything in `denied` fails, and is
+/// predicted to, as it would for another user's file.
+#[derive(Debug)]
+struct DenyingFs {
+    denied: Vec<PathBuf>,
+}
+
+impl DenyingFs {
+    fn check(&self, path: &Path) -> io::Result<()> {
+        if self.denied.iter().any(|denied| denied == path) {
+            return Err(io::ErrorKind::PermissionDenied.into());
+        }
+        Ok(())
+    }
+}
+
+impl FileSystem for DenyingFs {
+    fn symlink_metadata(&self, path: &Path) -> io::Result<Metadata> {
+        OsFs.symlink_metadata(path)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        OsFs.metadata(path)
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<DirEntry>> {
+        OsFs.read_dir(path)
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        OsFs.canonicalize(path)
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        self.check(path)?;
+        OsFs.remove_file(path)
+    }
+
+    fn remove_dir(&self, path: &Path) -> io::Result<()> {
+        self.check(path)?;
+        OsFs.remove_dir(path)
+    }
+
+    fn check_removable(&self, path: &Path) -> io::Result<()> {
+        self.check(path)?;
+        OsFs.check_removable(path)
+    }
+}
+
+/// The local disk, with everything below `volume` made to look like another
+/// device, also when reached through a symlink: renames out of it fail as
+/// they would across volumes, and removing anything in `denied` fails.
+#[derive(Debug)]
+struct OtherVolumeFs {
+    volume: PathBuf,
+    denied: Vec<PathBuf>,
+}
+
+impl OtherVolumeFs {
+    /// `follow` resolves the last component of `path` too.
+    fn fake_device(&self, path: &Path, follow: bool, mut metadata: Metadata) -> Metadata {
+        let resolved = if follow {
+            path.canonicalize().ok()
+        } else {
+            path.parent()
+                .and_then(|parent| parent.canonicalize().ok())
+                .zip(path.file_name())
+                .map(|(parent, name)| parent.join(name))
+        };
+        let volume = self.volume.canonicalize().unwrap_or_else(|_| self.volume.clone());
+        if resolved.as_deref().unwrap_or(path).starts_with(volume) {
+            metadata.device = Some(u64::MAX);
+        }
+        metadata
+    }
+
+    fn check(&self, path: &Path) -> io::Result<()> {
+        if self.denied.iter().any(|denied| denied == path) {
+            return Err(io::ErrorKind::PermissionDenied.into());
+        }
+        Ok(())
+    }
+}
+
+impl FileSystem for OtherVolumeFs {
+    fn symlink_metadata(&self, path: &Path) -> io::Result<Metadata> {
+        Ok(self.fake_device(path, false, OsFs.symlink_metadata(path)?))
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        Ok(self.fake_device(path, true, OsFs.metadata(path)?))
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<DirEntry>> {
+        let mut entries = OsFs.read_dir(path)?;
+        for entry in &mut entries {
+            entry.metadata = self.fake_device(&entry.path, false, entry.metadata.clone());
+        }
+        Ok(entries)
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        OsFs.canonicalize(path)
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        self.check(path)?;
+        OsFs.remove_file(path)
+    }
+
+    fn remove_dir(&self, path: &Path) -> io::Result<()> {
+        self.check(path)?;
+        OsFs.remove_dir(path)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        if from.starts_with(&self.volume) != to.starts_with(&self.volume) {
+            return Err(io::ErrorKind::CrossesDevices.into());
+        }
+        OsFs.rename(from, to)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        OsFs.create_dir_all(path)
+    }
+
+    fn copy_file(&self, from: &Path, to: &Path) -> io::Result<()> {
+        OsFs.copy_file(from, to)
+    }
+
+    fn copy_link(&self, from: &Path, to: &Path) -> io::Result<()> {
+        OsFs.copy_link(from, to)
+    }
+
+    fn copy_permissions(&self, from: &Path, to: &Path) -> io::Result<()> {
+        OsFs.copy_permissions(from, to)
+    }
+}
+
+fn scan(registry: &RuleRegistry, request: &EntryFilters) -> Vec<TempFile> {
+    let mut files = scan_entries(registry, request, &ScanControl::default(), &());
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    files
+}
+
+fn paths(files: &[TempFile]) -> Vec<String> {
+    files.iter().map(|f| f.path.clone()).collect()
+}
+
+fn names(files: &[TempFile]) -> Vec<&str> {
+    files.iter().map(|f| f.name.as_str()).collect()
+}
+
+#[test]
+fn scan_reports_top_level_entries_with_recursive_sizes() {
+    let fixture = Fixture::new("test", "scan");
+    fixture.file("cache/a.bin", 100);
+    fixture.file("cache/sub/b.bin", 200);
+    fixture.file("cache/sub/deep/c.bin", 300);
+    fixture.file("cache/empty.bin", 0);
+    let registry = fixture.registry(&[("fixture-cache", "cache")], "");
+
+    let recorder = Recorder::default();
+    let files = scan_entries(
+        &registry,
+        &EntryFilters::default(),
+        &ScanControl::default(),
+        &recorder,
+    );
+    let mut sorted = files.clone();
+    sorted.sort_by(|a, b| a.path.cmp(&b.path));
+
+    assert_eq!(names(&sorted), ["a.bin", "sub"]);
+    assert_eq!((sorted[0].kind, sorted[0].size), (EntryKind::File, 100));
+    assert_eq!((sorted[1].kind, sorted[1].size), (EntryKind::Dir, 500));
+    assert!(sorted.iter().all(|f| f.category_id == "fixture-cache"));
+
+    let partials = recorder.partials.lock().unwrap();
+    assert_eq!(partials.len(), 1);
+    assert_eq!(partials[0].total_size, 600);
+}
+
+#[test]
+fn request_filters_narrow_the_scan() {
+    let fixture = Fixture::new("test", "filters");
+    fixture.file("cache/small.tmp", 10);
+    fixture.file("cache/large.tmp", 1000);
+    fixture.file("cache/large.keep", 1000);
+    let registry = fixture.registry(&[("fixture-cache", "cache")], "");
+
+    let request = EntryFilters {
+        min_size: Some(100),
+        exclude: vec![NamePattern::new("*.keep").unwrap()],
+        ..EntryFilters::default()
+    };
+    assert_eq!(names(&scan(&registry, &request)), ["large.tmp"]);
+}
+
+#[test]
+fn invalid_filter_globs_are_reported_when_read() {
+    let fixture = Fixture::new("test", "bad-glob");
+    let config = fixture.dir("config");
+    fs::write(
+        config.join("rules.toml"),
+        format!(
+            "[[rules]]\nid = \"bad\"\npath = '{}'\ncategory = \"fixture-cache\"\n\
+             [rules.filters]\ninclude = [\"cache-[a\"]\n",
+            fixture.path("cache").display()
+        ),
+    )
+    .unwrap();
+    let error = RuleRegistry::load(Some(&config)).unwrap_err();
+    assert!(error.contains("cache-[a"), "{}", error);
+
+    let request = serde_json::from_str::<EntryFilters>(r#"{"exclude": ["*.keep", "[x"]}"#);
+    assert!(request.unwrap_err().to_string().contains("[x"));
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinked_roots_are_scanned_on_the_volume_they_lead_to() {
+    let fixture = Fixture::new("test", "linked-root");
+    fixture.file("elsewhere/top.bin", 100);
+    fixture.file("elsewhere/sub/a.bin", 500);
+    std::os::unix::fs::symlink(fixture.path("elsewhere"), fixture.path("cache")).unwrap();
+    let registry = fixture
+        .registry(&[("fixture-cache", "cache")], "")
+        .with_filesystem(Arc::new(OtherVolumeFs {
+            volume: fixture.path("elsewhere"),
+            denied: Vec::new(),
+        }));
+
+    let files = scan(&registry, &EntryFilters::default());
+    let found: Vec<(&str, u64)> = files.iter().map(|f| (f.name.as_str(), f.size)).collect();
+    assert_eq!(found, [("sub", 500), ("top.bin", 100)]);
+}
+
+#[test]
+fn nested_rule_roots_are_left_to_their_own_rule() {
+    let fixture = Fixture::new("test", "nested");
+    fixture.file("cache/a.bin", 100);
+    fixture.file("cache/logs/b.log", 200);
+    let mut registry = fixture.registry(
+        &[("fixture-cache", "cache"), ("fixture-logs", "cache/logs")],
+        "",
+    );
+
+    let files = scan(&registry, &EntryFilters::default());
+    let found: Vec<(&str, &str)> = files
+        .iter()
+        .map(|f| (f.name.as_str(), f.category_id.as_str()))
+        .collect();
+    assert_eq!(found, [("a.bin", "fixture-cache"), ("b.log", "fixture-logs")]);
+
+    // Also when the inner rule belongs to a family left out of the scan.
+    fixture.file("cache/pip/c.whl", 300);
+    let mut pip = CleaningRule::new(
+        "fixture-pip".to_string(),
+        fixture.path("cache/pip").display().to_string(),
+        "fixture-pip",
+    );
+    pip.family = RuleFamily::Developer;
+    registry.merge(vec![pip]);
+    registry.retain_families(&RuleFamily::DEFAULT_SCAN);
+    assert_eq!(names(&scan(&registry, &EntryFilters::default())), ["a.bin", "b.log"]);
+}
+
+#[test]
+fn clean_deletes_scanned_entries_and_reports_progress() {
+    let fixture = Fixture::new("test", "clean");
+    let file = fixture.file("cache/a.bin", 100);
+    fixture.file("cache/sub/b.bin", 200);
+    let registry = fixture.registry(&[("fixture-cache", "cache")], "");
+
+    let files = scan(&registry, &EntryFilters::default());
+    let session = ScanSession::new(&files);
+    let recorder = Arc::new(Recorder::default());
+    let result = delete_entries(
+        &registry,
+        &session,
+        &paths(&files),
+        &EntryFilters::default(),
+        true,
+        None,
+        &DeleteControl::observed(recorder.clone()),
+    );
+
+    assert_eq!(result.deleted_size, 300);
+    assert_eq!(result.deleted.len(), 2);
+    assert!(result.rejected.is_empty());
+    assert!(!file.exists());
+    assert!(!fixture.path("cache/sub").exists());
+    assert!(fixture.path("cache").is_dir(), "the rule root itself stays");
+
+    let progress = recorder.deletes.lock().unwrap();
+    assert_eq!(progress.len(), 2);
+    assert_eq!((progress[0].bytes_done, progress[0].bytes_total), (100, 300));
+    assert!((progress[0].percentage - 100.0 / 3.0).abs() < 0.01);
+    assert_eq!(progress[1].current, progress[1].total);
+    assert_eq!(progress[1].deleted_size, 300);
+    assert_eq!(progress[1].percentage, 100.0);
+    assert_eq!(progress[1].eta_seconds, Some(0));
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinks_are_reported_as_links_and_never_followed() {
+    use std::os::unix::fs::symlink;
+
+    let fixture = Fixture::new("test", "symlinks");
+    let target = fixture.file("outside/big.bin", 1000);
+    fixture.file("cache/sub/a.bin", 10);
+    symlink(fixture.path("outside"), fixture.path("cache/link")).unwrap();
+    symlink(fixture.path("outside"), fixture.path("cache/sub/escape")).unwrap();
+    let registry = fixture.registry(&[("fixture-cache", "cache")], "");
+
+    // Neither link is walked into, at the top or inside a directory.
+    let files = scan(&registry, &EntryFilters::default());
+    let found: Vec<(&str, EntryKind)> = files.iter().map(|f| (f.name.as_str(), f.kind)).collect();
+    assert_eq!(found, [("link", EntryKind::Link), ("sub", EntryKind::Dir)]);
+    assert_eq!(files[1].size, 10);
+
+    let result = delete_entries(
+        &registry,
+        &ScanSession::new(&files),
+        &paths(&files),
+        &EntryFilters::default(),
+        true,
+        None,
+        &DeleteControl::default(),
+    );
+    assert_eq!(result.deleted.len(), 2);
+    assert!(fs::symlink_metadata(fixture.path("cache/link")).is_err());
+    assert!(!fixture.path("cache/sub").exists());
+    assert_eq!(fs::read(&target).unwrap().len(), 1000, "only the links are removed");
+}
+
+#[cfg(unix)]
+#[test]
+fn paths_escaping_the_root_through_a_linked_parent_are_refused() {
+    let fixture = Fixture::new("test", "escape");
+    fixture.file("outside/big.bin", 1000);
+    fixture.file("cache/a.bin", 10);
+    std::os::unix::fs::symlink(fixture.path("outside"), fixture.path("cache/link")).unwrap();
+    let root = fixture.path("cache");
+
+    assert!(ensure_within_root(&OsFs, &fixture.path("cache/a.bin"), &root).is_ok());
+    // The link itself may go, but not what it leads to.
+    assert!(ensure_within_root(&OsFs, &fixture.path("cache/link"), &root).is_ok());
+    assert!(ensure_within_root(&OsFs, &fixture.path("cache/link/big.bin"), &root).is_err());
+    assert!(ensure_within_root(&OsFs, &fixture.path("cache/../outside/big.bin"), &root).is_err());
+    assert!(ensure_within_root(&OsFs, &root, &root).is_err());
+}
+
+#[test]
+fn cancelled_jobs_keep_what_they_finished() {
+    let fixture = Fixture::new("test", "cancel");
+    let first = fixture.file("cache/a.bin", 100);
+    let second = fixture.file("cache/b.bin", 100);
+    let registry = fixture.registry(&[("fixture-cache", "cache")], "");
+
+    let files = scan(&registry, &EntryFilters::default());
+    let jobs = Arc::new(DeleteJobs::default());
+    let canceller = jobs.clone();
+    let job = DeleteJob::new(DeleteControl::new(move |_| {
+        canceller.cancel(None);
+    }));
+    jobs.start(job.clone());
+    let result = delete_entries(
+        &registry,
+        &ScanSession::new(&files),
+        &paths(&files),
+        &EntryFilters::default(),
+        true,
+        None,
+        job.control(),
+    );
+    job.finish(Ok(result));
+
+    let status = jobs.get(None).unwrap().status();
+    assert_eq!(status.job_id, job.id());
+    assert_eq!(status.state, JobState::Cancelled);
+    assert_eq!(status.progress.unwrap().current, 1);
+    let report = status.report.unwrap();
+    assert_eq!((report.items.len(), report.deleted_size), (1, 100));
+    assert!(!first.exists() && second.exists());
+    assert!(!jobs.cancel(None), "nothing is left running");
+}
+
+#[test]
+fn the_last_eight_finished_jobs_are_kept() {
+    let jobs = DeleteJobs::default();
+    let finished: Vec<_> = (0..10)
+        .map(|_| {
+            let job = DeleteJob::new(DeleteControl::default());
+            jobs.start(job.clone());
+            job.finish(Err("falhou".to_string()));
+            job
+        })
+        .collect();
+    let running = DeleteJob::new(DeleteControl::default());
+    jobs.start(running.clone());
+
+    let kept: Vec<bool> = finished
+        .iter()
+        .map(|job| jobs.get(Some(job.id())).is_some())
+        .collect();
+    assert_eq!(kept, [false, false, true, true, true, true, true, true, true, true]);
+    assert_eq!(jobs.get(None).unwrap().id(), running.id());
+}
+
+#[test]
+fn clean_reports_the_outcome_of_each_path() {
+    let fixture = Fixture::new("test", "outcomes");
+    fixture.file("cache/a.bin", 100);
+    let gone = fixture.file("cache/gone.bin", 10);
+    let locked = fixture.file("cache/locked.bin", 50);
+    fixture.file("cache/sub/b.bin", 200);
+    let registry = fixture
+        .registry(&[("fixture-cache", "cache")], "")
+        .with_filesystem(Arc::new(DenyingFs {
+            denied: vec![locked.clone(), fixture.path("cache/sub")],
+        }));
+
+    let files = scan(&registry, &EntryFilters::default());
+    let session = ScanSession::new(&files);
+    fs::remove_file(&gone).unwrap();
+    let recorder = Arc::new(Recorder::default());
+    let result = delete_entries(
+        &registry,
+        &session,
+        &paths(&files),
+        &EntryFilters::default(),
+        true,
+        None,
+        &DeleteControl::observed(recorder.clone()),
+    );
+
+    let outcomes: Vec<_> = result
+        .items
+        .iter()
+        .map(|item| (item.outcome, item.error, item.files_removed, item.bytes_freed))
+        .collect();
+    assert_eq!(
+        outcomes,
+        [
+            (Outcome::Deleted, None, 1, 100),
+            (Outcome::Skipped, Some(FailureKind::NotFound), 0, 0),
+            (Outcome::Failed, Some(FailureKind::PermissionDenied), 0, 0),
+            // `b.bin` went before the directory holding it was refused.
+            (Outcome::PartiallyDeleted, Some(FailureKind::PermissionDenied), 1, 200),
+        ]
+    );
+    assert_eq!(result.items[1].rejection, Some(Rejection::Missing));
+    assert_eq!((result.removed_files, result.freed_bytes), (2, 300));
+    assert_eq!(result.deleted_size, 100);
+    assert!(locked.exists() && fixture.path("cache/sub").is_dir());
+
+    let progress = recorder.deletes.lock().unwrap();
+    let reported: Vec<Outcome> = progress.iter().map(|p| p.item.outcome).collect();
+    assert_eq!(reported, outcomes.iter().map(|o| o.0).collect::<Vec<_>>());
+}
+
+#[test]
+fn clean_keeps_going_past_entries_it_cannot_remove() {
+    let fixture = Fixture::new("test", "partial");
+    fixture.file("cache/dir/a.bin", 100);
+    let locked = fixture.file("cache/dir/locked/x.bin", 10);
+    fixture.file("cache/dir/locked/y.bin", 20);
+    fixture.file("cache/dir/sub/z.bin", 30);
+    let registry = fixture
+        .registry(&[("fixture-cache", "cache")], "")
+        .with_filesystem(Arc::new(DenyingFs {
+            denied: vec![locked.clone()],
+        }));
+
+    let files = scan(&registry, &EntryFilters::default());
+    let session = ScanSession::new(&files);
+    let recorder = Arc::new(Recorder::default());
+    let result = delete_entries(
+        &registry,
+        &session,
+        &paths(&files),
+        &EntryFilters::default(),
+        true,
+        None,
+        &DeleteControl::observed(recorder.clone()),
+    );
+
+    let item = &result.items[0];
+    assert_eq!(item.outcome, Outcome::PartiallyDeleted);
+    assert_eq!((item.files_removed, item.bytes_freed), (3, 150));
+    assert_eq!(item.failures.len(), 1);
+    assert_eq!(item.failures[0].path, locked.to_string_lossy());
+    assert_eq!(result.deleted_size, 0);
+    // What a partial deletion freed is counted all the same.
+    assert_eq!(result.freed_bytes, 150);
+    assert_eq!(recorder.deletes.lock().unwrap()[0].freed_bytes, 150);
+
+    // Only the locked file and the directories holding it are left.
+    assert!(locked.exists());
+    assert_eq!(fs::read_dir(fixture.path("cache/dir")).unwrap().count(), 1);
+    assert_eq!(fs::read_dir(fixture.path("cache/dir/locked")).unwrap().count(), 1);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn entries_held_open_are_marked_and_skipped() {
+    let fixture = Fixture::new("test", "in-use");
+    let free = fixture.file("cache/free.bin", 50);
+    let held = fixture.file("cache/sub/held.bin", 100);
+    let registry = fixture.registry(&[("fixture-cache", "cache")], "");
+    let open = fs::File::open(&held).unwrap();
+
+    // The directory is in use for the file open inside it.
+    let files = scan(&registry, &EntryFilters::default());
+    assert_eq!(names(&files), ["free.bin", "sub"]);
+    assert!(files[0].in_use.is_empty());
+    assert!(files[1].in_use.iter().any(|holder| holder.pid == std::process::id()));
+
+    let session = ScanSession::new(&files);
+    let clean = |include_in_use| {
+        delete_entries(
+            &registry,
+            &session,
+            &paths(&files),
+            &EntryFilters::default(),
+            include_in_use,
+            None,
+            &DeleteControl::default(),
+        )
+    };
+    let result = clean(false);
+    let outcomes: Vec<_> = result.items.iter().map(|i| (i.outcome, i.rejection)).collect();
+    assert_eq!(
+        outcomes,
+        [(Outcome::Deleted, None), (Outcome::Skipped, Some(Rejection::InUse))]
+    );
+    assert!(!free.exists() && held.exists());
+
+    // Unless asked to go ahead anyway.
+    let result = clean(true);
+    assert_eq!(result.items[1].outcome, Outcome::Deleted);
+    assert!(!held.exists());
+    drop(open);
+}
+
+#[test]
+fn clean_refuses_entries_not_scanned_missing_or_replaced() {
+    let fixture = Fixture::new("test", "refuse");
+    let missing = fixture.file("cache/missing.bin", 100);
+    let replaced = fixture.file("cache/replaced.bin", 100);
+    let registry = fixture.registry(&[("fixture-cache", "cache")], "");
+
+    let files = scan(&registry, &EntryFilters::default());
+    let session = ScanSession::new(&files);
+
+    let late = fixture.file("cache/late.bin", 100);
+    fs::remove_file(&missing).unwrap();
+    fs::remove_file(&replaced).unwrap();
+    fs::create_dir(&replaced).unwrap();
+    fs::write(replaced.join("inner.bin"), b"x").unwrap();
+
+    let requested: Vec<String> = [&late, &missing, &replaced]
+        .iter()
+        .map(|p| p.to_string_lossy().to_string())
+        .collect();
+    let result = delete_entries(
+        &registry,
+        &session,
+        &requested,
+        &EntryFilters::default(),
+        true,
+        None,
+        &DeleteControl::default(),
+    );
+
+    let reasons: Vec<Rejection> = result.rejected.iter().map(|r| r.reason).collect();
+    assert_eq!(
+        reasons,
+        [Rejection::NotScanned, Rejection::Missing, Rejection::Changed]
+    );
+    assert_eq!(result.deleted_size, 0);
+    assert!(late.exists() && replaced.join("inner.bin").exists());
+}
+
+#[test]
+fn clean_rechecks_filters_against_the_current_state() {
+    let fixture = Fixture::new("test", "recheck");
+    let file = fixture.file("cache/grows.bin", 100);
+    let registry = fixture.registry(&[("fixture-cache", "cache")], "");
+
+    let files = scan(&registry, &EntryFilters::default());
+    let session = ScanSession::new(&files);
+    let request = EntryFilters {
+        min_size: Some(50),
+        ..EntryFilters::default()
+    };
+    fs::write(&file, b"tiny").unwrap();
+
+    let result = delete_entries(
+        &registry,
+        &session,
+        &paths(&files),
+        &request,
+        true,
+        None,
+        &DeleteControl::default(),
+    );
+    assert_eq!(result.rejected[0].reason, Rejection::Filtered);
+    assert!(file.exists());
+}
+
+#[test]
+fn exclusions_are_skipped_by_scan_and_refused_by_clean() {
+    let fixture = Fixture::new("test", "exclusions");
+    fixture.file("cache/a.bin", 100);
+    fixture.file("cache/tool/keep/state.db", 100);
+    fixture.file("cache/b.log", 100);
+    fixture.file("cache/c.bin", 100);
+    fixture.file("cache/other/keep.txt", 100);
+    let exclusions = format!(
+        "[[exclusions]]\npath = '{}'\n\n\
+         [[exclusions]]\npath = '{}'\n\n\
+         [[exclusions]]\npath = '{}'\n\n\
+         [[exclusions]]\npath = '{}'\ncategories = [\"fixture-logs\"]\n",
+        fixture.path("cache/tool/keep").display(),
+        fixture.path("cache/*.log").display(),
+        fixture.path("cache/*/keep.txt").display(),
+        fixture.path("cache/c.bin").display(),
+    );
+
+    // `tool` contains a protected path, `b.log` matches a glob and `other`
+    // holds a file matching one; the `c.bin` exclusion is scoped to another
+    // category.
+    let registry = fixture.registry(&[("fixture-cache", "cache")], &exclusions);
+    assert_eq!(
+        names(&scan(&registry, &EntryFilters::default())),
+        ["a.bin", "c.bin"]
+    );
+
+    // Entries scanned before the exclusions existed are refused.
+    let unrestricted = fixture.registry(&[("fixture-cache", "cache")], "");
+    let files = scan(&unrestricted, &EntryFilters::default());
+    let session = ScanSession::new(&files);
+    let registry = fixture.registry(&[("fixture-cache", "cache")], &exclusions);
+    let result = delete_entries(
+        &registry,
+        &session,
+        &paths(&files),
+        &EntryFilters::default(),
+        true,
+        None,
+        &DeleteControl::default(),
+    );
+
+    let mut refused: Vec<(String, Rejection)> = result
+        .rejected
+        .iter()
+        .map(|r| (file_name(&r.path), r.reason))
+        .collect();
+    refused.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(
+        refused,
+        [
+            ("b.log".to_string(), Rejection::Excluded),
+            ("other".to_string(), Rejection::Excluded),
+            ("tool".to_string(), Rejection::Excluded),
+        ]
+    );
+    assert!(fixture.path("cache/tool/keep/state.db").exists());
+    assert!(fixture.path("cache/other/keep.txt").exists());
+    assert!(!fixture.path("cache/a.bin").exists());
+    assert!(!fixture.path("cache/c.bin").exists());
+}
+
+fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .unwrap()
+        .to_string_lossy()
+        .to_string()
+}
+
+#[test]
+fn dry_run_plans_the_removal_without_touching_anything() {
+    let fixture = Fixture::new("test", "dry-run");
+    fixture.file("cache/a.bin", 100);
+    fixture.file("cache/sub/b.bin", 200);
+    let locked = fixture.file("cache/sub/deep/c.bin", 300);
+    let registry = fixture
+        .registry(&[("fixture-cache", "cache")], "")
+        .with_filesystem(Arc::new(DenyingFs {
+            denied: vec![locked.clone()],
+        }));
+
+    let files = scan(&registry, &EntryFilters::default());
+    let session = ScanSession::new(&files);
+    let mut requested = paths(&files);
+    requested.push(fixture.path("cache/unknown.bin").to_string_lossy().to_string());
+    let result = plan_entries(
+        &registry,
+        &session,
+        &requested,
+        &EntryFilters::default(),
+        true,
+    );
+
+    assert!(result.dry_run);
+    assert_eq!(result.deleted_size, 0);
+    assert_eq!(result.rejected[0].reason, Rejection::NotScanned);
+
+    let file = &result.planned[0].removal;
+    assert_eq!((file.files, file.bytes, file.failed), (1, 100, 0));
+
+    // `c.bin` stays, and with it the two directories holding it.
+    let dir = &result.planned[1].removal;
+    assert_eq!((dir.files, dir.dirs, dir.bytes), (1, 0, 200));
+    assert_eq!(dir.failed, 1);
+    assert_eq!(dir.failures[0].path, locked.to_string_lossy());
+    assert_eq!(dir.failures[0].kind, FailureKind::PermissionDenied);
+
+    assert!(fixture.path("cache/a.bin").exists() && locked.exists());
+}
+
+#[test]
+fn quarantined_entries_can_be_restored_or_purged() {
+    let fixture = Fixture::new("test", "quarantine");
+    let file = fixture.file("cache/a.bin", 100);
+    fixture.file("cache/sub/b.bin", 200);
+    let registry = fixture.registry(&[("fixture-cache", "cache")], "");
+    let data = fixture.path("data");
+
+    let files = scan(&registry, &EntryFilters::default());
+    let mut quarantine = Quarantine::open(&data).unwrap();
+    let result = delete_entries(
+        &registry,
+        &ScanSession::new(&files),
+        &paths(&files),
+        &EntryFilters::default(),
+        true,
+        Some(&mut quarantine),
+        &DeleteControl::default(),
+    );
+    assert!(result.quarantined);
+    assert_eq!(result.deleted_size, 300);
+    assert!(!file.exists() && !fixture.path("cache/sub").exists());
+
+    // The manifest survives reopening.
+    drop(quarantine);
+    let mut quarantine = Quarantine::open(&data).unwrap();
+    let list = quarantine.list();
+    assert_eq!(list.total_size, 300);
+    let id_of = |name: &str| {
+        let item = list.items.iter().find(|i| i.entry.name == name).unwrap();
+        item.id.clone()
+    };
+    let ids = [id_of("a.bin"), id_of("sub")];
+
+    // Restoring refuses to overwrite what took the original path.
+    fs::write(&file, b"new").unwrap();
+    let catalog = Catalog::get(Locale::En);
+    let restored = quarantine.restore(&OsFs, &ids[..1], catalog);
+    assert_eq!(restored.failed.len(), 1);
+    fs::remove_file(&file).unwrap();
+
+    let restored = quarantine.restore(&OsFs, &ids, catalog);
+    assert_eq!(restored.restored.len(), 2);
+    assert_eq!(fs::read(&file).unwrap().len(), 100);
+    assert_eq!(fs::read(fixture.path("cache/sub/b.bin")).unwrap().len(), 200);
+    assert!(quarantine.list().items.is_empty());
+
+    // Retention purges the oldest items beyond the size limit.
+    for file in scan(&registry, &EntryFilters::default()) {
+        assert!(quarantine.store(&registry, file).is_complete());
+    }
+    let purged = quarantine
+        .set_retention(
+            &OsFs,
+            Retention {
+                max_age_days: None,
+                max_size: Some(250),
+            },
+        )
+        .unwrap();
+    assert_eq!(purged.purged.len(), 1);
+    assert_eq!(purged.purged[0].entry.name, "a.bin");
+    assert_eq!(quarantine.list().total_size, 200);
+    drop(quarantine);
+
+    let emptied = Quarantine::open(&data).unwrap().purge(&OsFs, None);
+    assert_eq!((emptied.purged.len(), emptied.freed), (1, 200));
+    assert!(Quarantine::open(&data).unwrap().list().items.is_empty());
+}
+
+#[test]
+fn quarantine_copies_across_volumes_and_keeps_partly_removed_items() {
+    let fixture = Fixture::new("test", "quarantine-volume");
+    let locked = fixture.file("volume/dir/locked.bin", 10);
+    fixture.file("volume/dir/b.bin", 20);
+    let registry = fixture
+        .registry(&[("fixture-cache", "volume")], "")
+        .with_filesystem(Arc::new(OtherVolumeFs {
+            volume: fixture.path("volume"),
+            denied: vec![locked.clone()],
+        }));
+    let data = fixture.path("data");
+
+    let files = scan(&registry, &EntryFilters::default());
+    let mut quarantine = Quarantine::open(&data).unwrap();
+    let removed = quarantine.store(&registry, files[0].clone());
+
+    // The copy is whole, so the item is kept; the original is reported as
+    // partly removed.
+    assert!(!removed.is_complete());
+    assert_eq!((removed.files, removed.bytes), (1, 20));
+    assert_eq!(removed.failures[0].path, locked.to_string_lossy());
+    assert!(locked.exists() && !fixture.path("volume/dir/b.bin").exists());
+
+    // The top of the other volume is a rule root, as a tmpfs `/tmp` is, so
+    // the item went to the data dir rather than to a store the next scan
+    // would offer.
+    let list = quarantine.list();
+    assert_eq!(list.items.len(), 1);
+    let stored = Path::new(&list.items[0].stored_path);
+    assert!(stored.starts_with(&data));
+    assert!(stored.join("locked.bin").is_file() && stored.join("b.bin").is_file());
+    assert!(!fixture.path("volume").read_dir().unwrap().any(|e| {
+        e.unwrap().file_name().to_string_lossy().starts_with(".zcleaner-quarantine")
+    }));
+
+    // Purging goes through the file system it is given.
+    let refusing = OtherVolumeFs {
+        volume: fixture.path("volume"),
+        denied: vec![stored.join("b.bin")],
+    };
+    assert!(quarantine.purge(&refusing, None).purged.is_empty());
+    assert!(stored.join("b.bin").exists());
+    assert_eq!(quarantine.purge(registry.filesystem(), None).purged.len(), 1);
+    assert!(!stored.exists());
+}
+
+#[test]
+fn the_quarantine_is_open_in_one_place_at_a_time() {
+    let fixture = Fixture::new("test", "quarantine-lock");
+    fixture.file("cache/a.bin", 10);
+    let registry = fixture.registry(&[("fixture-cache", "cache")], "");
+    let data = fixture.path("data");
+
+    let mut quarantine = Quarantine::open(&data).unwrap();
+    let files = scan(&registry, &EntryFilters::default());
+    assert!(quarantine.store(&registry, files[0].clone()).is_complete());
+
+    // A second open, as the CLI would make while the app cleans, waits for
+    // the first to be dropped and then sees its changes.
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let waiting = data.clone();
+    let opener = std::thread::spawn(move || {
+        let quarantine = Quarantine::open(&waiting).unwrap();
+        sender.send(quarantine.list().items.len()).unwrap();
+    });
+    let timeout = std::time::Duration::from_millis(300);
+    assert!(receiver.recv_timeout(timeout).is_err());
+    // Listing doesn't wait.
+    assert_eq!(Quarantine::list_in(&data).unwrap().items.len(), 1);
+
+    drop(quarantine);
+    assert_eq!(receiver.recv_timeout(std::time::Duration::from_secs(10)), Ok(1));
+    opener.join().unwrap();
+}
+
+#[test]
+fn mount_points_inside_an_entry_are_left_and_reported() {
+    let fixture = Fixture::new("test", "mount-point");
+    fixture.file("cache/tool/a.bin", 10);
+    fixture.file("cache/tool/mnt/b.bin", 20);
+    let fs = OtherVolumeFs {
+        volume: fixture.path("cache/tool/mnt"),
+        denied: Vec::new(),
+    };
+
+    let plan = plan_removal(&fs, &fixture.path("cache/tool"), true);
+    assert_eq!((plan.files, plan.bytes, plan.failed), (1, 10, 1));
+    assert_eq!(plan.failures[0].kind, FailureKind::MountPoint);
+
+    let removed = remove_path(&fs, &fixture.path("cache/tool"), true);
+    assert!(!removed.is_complete());
+    assert_eq!((removed.files, removed.bytes), (1, 10));
+    assert_eq!(removed.first_failure(), Some(FailureKind::MountPoint));
+    assert_eq!(removed.failures[0].path, fixture.path("cache/tool/mnt").to_string_lossy());
+    assert!(fixture.path("cache/tool/mnt/b.bin").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn quarantine_copies_leave_pipes_and_sockets_out() {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::net::UnixListener;
+
+    let fixture = Fixture::new("test", "quarantine-fifo");
+    fixture.file("volume/dir/a.bin", 10);
+    let pipe = fixture.path("volume/dir/pipe");
+    let name = CString::new(pipe.as_os_str().as_bytes()).unwrap();
+    assert_eq!(unsafe { libc::mkfifo(name.as_ptr(), 0o600) }, 0);
+    let _socket = UnixListener::bind(fixture.path("volume/dir/socket")).unwrap();
+    let registry = fixture
+        .registry(&[("fixture-cache", "volume")], "")
+        .with_filesystem(Arc::new(OtherVolumeFs {
+            volume: fixture.path("volume"),
+            denied: Vec::new(),
+        }));
+
+    let files = scan(&registry, &EntryFilters::default());
+    let mut quarantine = Quarantine::open(&fixture.path("data")).unwrap();
+    let removed = quarantine.store(&registry, files[0].clone());
+
+    // Copying the pipe would block forever; both are dropped with the rest
+    // of the original once the copy is done.
+    assert!(removed.is_complete());
+    assert!(!fixture.path("volume/dir").exists());
+    let list = quarantine.list();
+    let stored = Path::new(&list.items[0].stored_path);
+    assert!(stored.join("a.bin").is_file());
+    assert!(!stored.join("pipe").exists() && !stored.join("socket").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn read_only_directories_of_the_user_are_planned_as_removable() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let fixture = Fixture::new("test", "read-only-dir");
+    let file = fixture.file("cache/ro/a.bin", 10);
+    let dir = fixture.path("cache/ro");
+    fs::set_permissions(&dir, fs::Permissions::from_mode(0o555)).unwrap();
+    // Unlinking from the read-only directory is refused, as it is for
+    // anyone but root, until the directory is made writable.
+    let denying = DenyingFs { denied: vec![file] };
+
+    let plan = plan_removal(&denying, &dir, false);
+    assert_eq!((plan.files, plan.dirs, plan.failed), (1, 1, 0));
+
+    let removed = remove_path(&OsFs, &dir, false);
+    assert!(removed.is_complete() && !dir.exists());
+}
@@ -0,0 +1,99 @@
+//! Sizes fixture directory trees for the disk usage explorer.
+
+mod common;
+
+use std::fs;
+use std::sync::Arc;
+
+use common::Fixture;
+use zcleaner_core::scan::ScanControl;
+use zcleaner_core::usage::{DirTree, UsageCache, UsageKind, UsageNode};
+
+impl Fixture {
+    /// a.bin (100), sub/b.bin (200), sub/deep/c.bin (300) and empty/.
+    fn sample(name: &str) -> Fixture {
+        let tree = Fixture::new("usage", name);
+        tree.file("a.bin", 100);
+        tree.file("sub/b.bin", 200);
+        tree.file("sub/deep/c.bin", 300);
+        tree.dir("empty");
+        tree
+    }
+
+    fn build(&self, relative: &str) -> Arc<DirTree> {
+        Arc::new(DirTree::build(&self.path(relative), &ScanControl::default()))
+    }
+}
+
+/// `(name, kind, size, files, dirs)` of each child.
+fn children(node: &UsageNode) -> Vec<(&str, UsageKind, u64, u64, u64)> {
+    node.children
+        .iter()
+        .map(|c| (c.name.as_str(), c.kind, c.size, c.files, c.dirs))
+        .collect()
+}
+
+#[test]
+fn directories_total_everything_below_them() {
+    let tree = Fixture::sample("totals");
+    let root = tree.build("");
+
+    let node = root.node(&tree.dir, 2, 20);
+    assert_eq!((node.size, node.files, node.dirs), (600, 3, 3));
+    assert_eq!(
+        children(&node),
+        [
+            ("sub", UsageKind::Dir, 500, 2, 1),
+            ("a.bin", UsageKind::File, 100, 0, 0),
+            ("empty", UsageKind::Dir, 0, 0, 0),
+        ]
+    );
+    // `deep` is at the depth limit: sized, but its children left out.
+    let deep = &node.children[0].children[0];
+    assert_eq!((deep.name.as_str(), deep.size), ("deep", 300));
+    assert!(deep.truncated && deep.children.is_empty());
+    assert!(!node.children[2].truncated);
+
+    // Past `top_n`, children are summed into one `other` node.
+    let node = root.node(&tree.dir, 1, 1);
+    assert_eq!(
+        children(&node),
+        [
+            ("sub", UsageKind::Dir, 500, 2, 1),
+            ("other", UsageKind::Other, 100, 1, 1),
+        ]
+    );
+}
+
+#[test]
+fn cached_trees_serve_subfolders_until_replaced() {
+    let tree = Fixture::sample("cache");
+    let cache = UsageCache::default();
+    cache.insert(tree.dir.clone(), tree.build(""));
+
+    // Drilling down reuses the cached walk, so a new file goes unseen.
+    tree.file("sub/new.bin", 1000);
+    let sub = cache.node(&tree.path("sub"), 1, 20).unwrap();
+    assert_eq!((sub.size, sub.files), (500, 2));
+    assert!(cache.node(&tree.path("missing"), 1, 20).is_none());
+    assert!(cache.node(&std::env::temp_dir(), 1, 20).is_none());
+
+    // A fresh analysis of a subfolder wins for that subfolder only.
+    cache.insert(tree.path("sub"), tree.build("sub"));
+    assert_eq!(cache.node(&tree.path("sub"), 1, 20).unwrap().size, 1500);
+    assert_eq!(cache.node(&tree.dir, 1, 20).unwrap().size, 600);
+
+    // A fresh analysis of the root replaces both.
+    fs::remove_file(tree.path("a.bin")).unwrap();
+    cache.insert(tree.dir.clone(), tree.build(""));
+    assert_eq!(cache.node(&tree.dir, 1, 20).unwrap().size, 1500);
+    assert_eq!(cache.node(&tree.path("sub/deep"), 1, 20).unwrap().size, 300);
+
+    // Only the most recent trees are kept.
+    for name in ["t1", "t2", "t3", "t4"] {
+        tree.file(&format!("{}/f.bin", name), 10);
+        cache.insert(tree.path(name), tree.build(name));
+    }
+    assert!(cache.node(&tree.dir, 1, 20).is_none());
+    assert_eq!(cache.node(&tree.path("t1"), 1, 20).unwrap().size, 10);
+}
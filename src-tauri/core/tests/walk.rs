@@ -0,0 +1,94 @@
+//! Sizes directory trees holding hard links and loops.
+
+mod common;
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use common::Fixture;
+use zcleaner_core::filesystem::{DirEntry, FileSystem, Metadata, OsFs};
+use zcleaner_core::scan::ScanControl;
+use zcleaner_core::walk::{DirStats, Walker};
+
+/// The local disk, with `bind` showing what is in `target`, as a bind mount
+/// would.
+#[derive(Debug)]
+struct BindFs {
+    bind: PathBuf,
+    target: PathBuf,
+}
+
+impl BindFs {
+    fn real(&self, path: &Path) -> PathBuf {
+        match path.strip_prefix(&self.bind) {
+            Ok(rest) => self.target.join(rest),
+            Err(_) => path.to_path_buf(),
+        }
+    }
+}
+
+impl FileSystem for BindFs {
+    fn symlink_metadata(&self, path: &Path) -> io::Result<Metadata> {
+        OsFs.symlink_metadata(&self.real(path))
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        OsFs.metadata(&self.real(path))
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<DirEntry>> {
+        let mut entries = OsFs.read_dir(&self.real(path))?;
+        for entry in &mut entries {
+            entry.path = path.join(&entry.name);
+            entry.metadata = self.symlink_metadata(&entry.path)?;
+        }
+        Ok(entries)
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        OsFs.canonicalize(&self.real(path))
+    }
+
+    fn remove_file(&self, _path: &Path) -> io::Result<()> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    fn remove_dir(&self, _path: &Path) -> io::Result<()> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+}
+
+fn counts(stats: DirStats) -> (u64, u64, u64) {
+    (stats.files, stats.dirs, stats.bytes)
+}
+
+#[cfg(unix)]
+#[test]
+fn hard_linked_files_are_counted_once() {
+    let tree = Fixture::new("walk", "hard-links");
+    let a = tree.file("a.bin", 100);
+    tree.file("sub/b.bin", 200);
+    fs::hard_link(&a, tree.path("a-link.bin")).unwrap();
+    fs::hard_link(&a, tree.path("sub/a-link.bin")).unwrap();
+
+    let stats = Walker::new(&ScanControl::default()).walk(&tree.dir);
+    assert_eq!(counts(stats), (2, 1, 300));
+}
+
+#[test]
+fn loops_are_walked_once() {
+    let tree = Fixture::new("walk", "loop");
+    tree.file("a.bin", 100);
+    tree.file("sub/b.bin", 200);
+    tree.dir("sub/loop");
+    // `sub/loop` leads back to the top, so without the visited set the walk
+    // would go round until the depth limit.
+    let fs = BindFs {
+        bind: tree.path("sub/loop"),
+        target: tree.dir.clone(),
+    };
+
+    let stats = Walker::new(&ScanControl::default()).filesystem(&fs).walk(&tree.dir);
+    assert_eq!(counts(stats), (2, 2, 300));
+}
@@ -1,483 +1,564 @@
 use serde::{Deserialize, Serialize};
-use std::fs;
-use std::path::PathBuf;
-use sysinfo::{Disks, System};
-use tauri::Emitter;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use sysinfo::System;
+use tauri::{Emitter, Manager};
+
+use zcleaner_core::artifacts::{self, ArtifactFinder, ArtifactQuery, ProjectInfo};
+use zcleaner_core::cleaning::{DeleteControl, DeleteJob, DeleteJobStatus, DeleteJobs};
+use zcleaner_core::duplicates::{
+    DuplicateFinder, DuplicateProgress, DuplicateQuery, DuplicateScanResult, DuplicateSession,
+    Resolution, ResolveMode, ResolveResult,
+};
+use zcleaner_core::exclusions::{Exclusion, Exclusions};
+use zcleaner_core::filesystem::OsFs;
+use zcleaner_core::filters::{self, EntryFilters};
+use zcleaner_core::i18n::{I18n, Locale};
+use zcleaner_core::open_files::OpenFiles;
+use zcleaner_core::quarantine::{
+    PurgeResult, Quarantine, QuarantineList, Retention, RestoreResult,
+};
+use zcleaner_core::report::{Report, ReportFormat, ReportSource};
+use zcleaner_core::roots::RootResolver;
+use zcleaner_core::rules::{self, RuleFamily, RuleRegistry};
+use zcleaner_core::scan::{ScanControl, ScanJobs, ScanPartial, ScanProgress};
+use zcleaner_core::session::{ScanSession, ScanSessions, Sessions};
+use zcleaner_core::usage::{DirTree, UsageCache, UsageNode, UsageQuery};
+use zcleaner_core::{
+    delete_entries, disk_info, list_processes, plan_entries, scan_entries, system_info,
+    terminate_process, DeleteProgress, DeleteReport, DiskInfo, ProcessInfo, ProgressObserver,
+    ScanResult, SystemInfo, TempFile,
+};
+
+/// Forwards core progress to the frontend as events.
+struct Events(tauri::AppHandle);
+
+impl ProgressObserver for Events {
+    fn scan_progress(&self, progress: ScanProgress) {
+        let _ = self.0.emit("scan-progress", progress);
+    }
 
-#[cfg(target_os = "windows")]
-fn is_elevated() -> bool {
-    use std::process::Command;
-    
-    let output = Command::new("net")
-        .args(&["session"])
-        .output();
-    
-    match output {
-        Ok(output) => output.status.success(),
-        Err(_) => false,
+    fn scan_partial(&self, partial: ScanPartial) {
+        let _ = self.0.emit("scan-partial", partial);
+    }
+
+    fn delete_progress(&self, progress: DeleteProgress) {
+        let _ = self.0.emit("delete-progress", progress);
+    }
+
+    fn duplicate_progress(&self, progress: DuplicateProgress) {
+        let _ = self.0.emit("duplicate-progress", progress);
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct TempFile {
-    pub path: String,
-    pub name: String,
-    pub size: u64,
-    pub category: String,
-    pub root_path: String,
+fn load_rules(app: &tauri::AppHandle) -> Result<RuleRegistry, String> {
+    let config_dir = app.path().app_config_dir().ok();
+    RuleRegistry::load(config_dir.as_deref())
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ScanResult {
+#[tauri::command]
+async fn scan_temp_files(
+    app: tauri::AppHandle,
+    jobs: tauri::State<'_, ScanJobs>,
+    sessions: tauri::State<'_, ScanSessions>,
+    i18n: tauri::State<'_, I18n>,
+    filters: Option<EntryFilters>,
+    families: Option<Vec<RuleFamily>>,
+) -> Result<ScanResult, String> {
+    let request = filters.unwrap_or_default();
+    let mut registry = load_rules(&app)?;
+    registry.retain_families(&families.unwrap_or_else(|| RuleFamily::DEFAULT_SCAN.to_vec()));
+    registry.localize(i18n.catalog());
+
+    let events = Arc::new(Events(app));
+    let control = Arc::new(ScanControl::observed(events.clone()));
+    jobs.start(control.clone());
+
+    let scan_control = control.clone();
+    let files = tauri::async_runtime::spawn_blocking(move || {
+        scan_entries(&registry, &request, &scan_control, events.as_ref())
+    })
+    .await;
+    jobs.finish(&control);
+
+    let files = files.map_err(|e| format!("Erro ao escanear: {}", e))?;
+    let total_size = files.iter().map(|f| f.size).sum();
+    let session_id = sessions.insert(ScanSession::new(&files));
+    Ok(ScanResult {
+        session_id,
+        files,
+        total_size,
+        cancelled: control.is_cancelled(),
+        scanned_at: Some(filters::now_millis()),
+    })
+}
+
+#[derive(Debug, Serialize)]
+pub struct ArtifactScanResult {
+    /// Pass this back to `delete_temp_files`, like a regular scan.
+    pub session_id: String,
+    /// Build output folders; `root_path` is the project they belong to.
     pub files: Vec<TempFile>,
+    pub projects: Vec<ProjectInfo>,
     pub total_size: u64,
+    pub cancelled: bool,
 }
 
-fn get_dir_size(path: &PathBuf) -> u64 {
-    let mut total = 0;
-    if let Ok(entries) = fs::read_dir(path) {
-        for entry in entries.flatten() {
-            if let Ok(metadata) = entry.metadata() {
-                if metadata.is_file() {
-                    total += metadata.len();
-                } else if metadata.is_dir() {
-                    total += get_dir_size(&entry.path());
-                }
+/// Looks for build output (`target/`, `node_modules/`, Gradle `build/` and
+/// `.gradle`, `__pycache__`, `.venv`) of the projects below `query.roots`.
+#[tauri::command]
+async fn find_build_artifacts(
+    app: tauri::AppHandle,
+    jobs: tauri::State<'_, ScanJobs>,
+    sessions: tauri::State<'_, ScanSessions>,
+    i18n: tauri::State<'_, I18n>,
+    query: ArtifactQuery,
+) -> Result<ArtifactScanResult, String> {
+    let roots: Vec<PathBuf> = query.roots.iter().map(PathBuf::from).collect();
+    if let Some(missing) = roots.iter().find(|root| !root.is_absolute() || !root.is_dir()) {
+        return Err(format!("Pasta não encontrada: {}", missing.display()));
+    }
+    let ArtifactQuery {
+        filters: request,
+        stale_days,
+        sort,
+        ..
+    } = query;
+    let registry = load_rules(&app)?;
+    let catalog = i18n.catalog();
+
+    let control = Arc::new(ScanControl::observed(Arc::new(Events(app.clone()))));
+    jobs.start(control.clone());
+
+    let scan_control = control.clone();
+    let found = tauri::async_runtime::spawn_blocking(move || {
+        let finder = ArtifactFinder::new(&scan_control, catalog)
+            .stale_days(stale_days)
+            .exclusions(registry.exclusions());
+        let open_files = OpenFiles::snapshot();
+        scan_control.set_rules_total(roots.len());
+
+        let mut files = Vec::new();
+        let mut projects = Vec::new();
+        for root in &roots {
+            if scan_control.is_cancelled() {
+                break;
             }
+            let root_name = root.to_string_lossy();
+            scan_control.begin(&root_name, "build-artifacts", &root_name);
+            let (found, found_projects) = finder.find(root, &[registry.defaults(), &request]);
+            scan_control.finish_rule();
+            files.extend(found);
+            projects.extend(found_projects);
         }
-    }
-    total
+        open_files.mark(&mut files);
+        artifacts::sort(&mut files, &mut projects, sort);
+        (files, projects)
+    })
+    .await;
+    jobs.finish(&control);
+
+    let (files, projects) = found.map_err(|e| format!("Erro ao escanear: {}", e))?;
+    let rules = projects
+        .iter()
+        .map(|project| artifacts::project_rule(Path::new(&project.path)))
+        .collect();
+    let session_id = sessions.insert(ScanSession::with_rules(&files, rules));
+    Ok(ArtifactScanResult {
+        session_id,
+        total_size: files.iter().map(|f| f.size).sum(),
+        files,
+        projects,
+        cancelled: control.is_cancelled(),
+    })
 }
 
+/// Finds files with identical content below `query.roots`, reporting
+/// `duplicate-progress` while it runs. Stopped by `cancel_scan`.
 #[tauri::command]
-fn scan_temp_files() -> Result<ScanResult, String> {
-    let mut files = Vec::new();
-    let mut total_size = 0u64;
-
-    // Windows Temp folders
-    if let Ok(temp_dir) = std::env::var("TEMP") {
-        let temp_path = PathBuf::from(&temp_dir);
-        let root_path = temp_path.to_string_lossy().to_string();
-        if let Ok(entries) = fs::read_dir(&temp_path) {
-            for entry in entries.flatten() {
-                if let Ok(metadata) = entry.metadata() {
-                    let size = if metadata.is_file() {
-                        metadata.len()
-                    } else if metadata.is_dir() {
-                        get_dir_size(&entry.path())
-                    } else {
-                        0
-                    };
-
-                    if size > 0 {
-                        files.push(TempFile {
-                            path: entry.path().to_string_lossy().to_string(),
-                            name: entry.file_name().to_string_lossy().to_string(),
-                            size,
-                            category: "Arquivos Temporários do Windows".to_string(),
-                            root_path: root_path.clone(),
-                        });
-                        total_size += size;
-                    }
-                }
-            }
-        }
+async fn find_duplicates(
+    app: tauri::AppHandle,
+    jobs: tauri::State<'_, ScanJobs>,
+    sessions: tauri::State<'_, Sessions<DuplicateSession>>,
+    query: DuplicateQuery,
+) -> Result<DuplicateScanResult, String> {
+    let roots: Vec<PathBuf> = query.roots.iter().map(PathBuf::from).collect();
+    if let Some(missing) = roots.iter().find(|root| !root.is_absolute() || !root.is_dir()) {
+        return Err(format!("Pasta não encontrada: {}", missing.display()));
     }
 
-    // Windows System Temp folder
-    let system_temp_path = PathBuf::from("C:\\Windows\\Temp");
-    if system_temp_path.exists() {
-        let root_path = system_temp_path.to_string_lossy().to_string();
-        if let Ok(entries) = fs::read_dir(&system_temp_path) {
-            for entry in entries.flatten() {
-                if let Ok(metadata) = entry.metadata() {
-                    let size = if metadata.is_file() {
-                        metadata.len()
-                    } else if metadata.is_dir() {
-                        get_dir_size(&entry.path())
-                    } else {
-                        0
-                    };
-
-                    if size > 0 {
-                        files.push(TempFile {
-                            path: entry.path().to_string_lossy().to_string(),
-                            name: entry.file_name().to_string_lossy().to_string(),
-                            size,
-                            category: "Arquivos Temporários do Sistema".to_string(),
-                            root_path: root_path.clone(),
-                        });
-                        total_size += size;
-                    }
-                }
-            }
-        }
-    }
+    let control = Arc::new(ScanControl::default());
+    jobs.start(control.clone());
 
-    // Windows Prefetch
-    let prefetch_path = PathBuf::from("C:\\Windows\\Prefetch");
-    if prefetch_path.exists() {
-        let root_path = prefetch_path.to_string_lossy().to_string();
-        if let Ok(entries) = fs::read_dir(&prefetch_path) {
-            for entry in entries.flatten() {
-                if let Ok(metadata) = entry.metadata() {
-                    if metadata.is_file() {
-                        let size = metadata.len();
-                        files.push(TempFile {
-                            path: entry.path().to_string_lossy().to_string(),
-                            name: entry.file_name().to_string_lossy().to_string(),
-                            size,
-                            category: "Prefetch do Windows".to_string(),
-                            root_path: root_path.clone(),
-                        });
-                        total_size += size;
-                    }
-                }
-            }
-        }
-    }
+    let scan_control = control.clone();
+    let session = tauri::async_runtime::spawn_blocking(move || {
+        DuplicateFinder::new(&scan_control, &query.filters, &Events(app)).find(&roots)
+    })
+    .await;
+    jobs.finish(&control);
+
+    let session = session.map_err(|e| format!("Erro ao buscar duplicatas: {}", e))?;
+    let sets = session.sets.clone();
+    let session_id = sessions.insert(session);
+    Ok(DuplicateScanResult {
+        session_id,
+        wasted_size: sets.iter().map(|set| set.wasted).sum(),
+        sets,
+        cancelled: control.is_cancelled(),
+    })
+}
 
-    // Windows Update Downloads
-    let windows_update_path = PathBuf::from("C:\\Windows\\SoftwareDistribution\\Download");
-    if windows_update_path.exists() {
-        let root_path = windows_update_path.to_string_lossy().to_string();
-        if let Ok(entries) = fs::read_dir(&windows_update_path) {
-            for entry in entries.flatten() {
-                if let Ok(metadata) = entry.metadata() {
-                    let size = if metadata.is_file() {
-                        metadata.len()
-                    } else if metadata.is_dir() {
-                        get_dir_size(&entry.path())
-                    } else {
-                        0
-                    };
-
-                    if size > 0 {
-                        files.push(TempFile {
-                            path: entry.path().to_string_lossy().to_string(),
-                            name: entry.file_name().to_string_lossy().to_string(),
-                            size,
-                            category: "Downloads do Windows Update".to_string(),
-                            root_path: root_path.clone(),
-                        });
-                        total_size += size;
-                    }
-                }
-            }
+/// Deletes the extra copies of duplicate sets, or replaces them with hard
+/// links or reflinks to the copy kept.
+#[tauri::command]
+async fn resolve_duplicates(
+    sessions: tauri::State<'_, Sessions<DuplicateSession>>,
+    i18n: tauri::State<'_, I18n>,
+    session_id: String,
+    resolutions: Vec<Resolution>,
+    mode: ResolveMode,
+) -> Result<ResolveResult, String> {
+    let session = sessions
+        .get(&session_id)
+        .ok_or_else(|| "Sessão de busca desconhecida ou expirada".to_string())?;
+    let catalog = i18n.catalog();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut result = ResolveResult::default();
+        for resolution in &resolutions {
+            session.resolve(resolution, mode, catalog, &mut result);
         }
-    }
+        result
+    })
+    .await
+    .map_err(|e| format!("Erro ao resolver duplicatas: {}", e))
+}
 
-    // Windows Logs
-    let windows_logs_path = PathBuf::from("C:\\Windows\\Logs");
-    if windows_logs_path.exists() {
-        let root_path = windows_logs_path.to_string_lossy().to_string();
-        if let Ok(entries) = fs::read_dir(&windows_logs_path) {
-            for entry in entries.flatten() {
-                if let Ok(metadata) = entry.metadata() {
-                    let size = if metadata.is_file() {
-                        metadata.len()
-                    } else if metadata.is_dir() {
-                        get_dir_size(&entry.path())
-                    } else {
-                        0
-                    };
-
-                    if size > 0 {
-                        files.push(TempFile {
-                            path: entry.path().to_string_lossy().to_string(),
-                            name: entry.file_name().to_string_lossy().to_string(),
-                            size,
-                            category: "Logs do Windows".to_string(),
-                            root_path: root_path.clone(),
-                        });
-                        total_size += size;
-                    }
-                }
-            }
-        }
-    }
+#[tauri::command]
+fn cancel_scan(jobs: tauri::State<'_, ScanJobs>) -> bool {
+    jobs.cancel()
+}
 
-    // Windows Minidump (crash dumps)
-    let minidump_path = PathBuf::from("C:\\Windows\\Minidump");
-    if minidump_path.exists() {
-        let root_path = minidump_path.to_string_lossy().to_string();
-        if let Ok(entries) = fs::read_dir(&minidump_path) {
-            for entry in entries.flatten() {
-                if let Ok(metadata) = entry.metadata() {
-                    if metadata.is_file() {
-                        let size = metadata.len();
-                        files.push(TempFile {
-                            path: entry.path().to_string_lossy().to_string(),
-                            name: entry.file_name().to_string_lossy().to_string(),
-                            size,
-                            category: "Dumps de Crash".to_string(),
-                            root_path: root_path.clone(),
-                        });
-                        total_size += size;
-                    }
-                }
-            }
-        }
-    }
+#[tauri::command]
+fn list_cleaning_rules(
+    app: tauri::AppHandle,
+    i18n: tauri::State<'_, I18n>,
+) -> Result<Vec<rules::CleaningRule>, String> {
+    let mut registry = load_rules(&app)?;
+    registry.localize(i18n.catalog());
+    Ok(registry.rules().to_vec())
+}
 
-    // Windows Error Reporting
-    let wer_path = PathBuf::from("C:\\ProgramData\\Microsoft\\Windows\\WER");
-    if wer_path.exists() {
-        let root_path = wer_path.to_string_lossy().to_string();
-        if let Ok(entries) = fs::read_dir(&wer_path) {
-            for entry in entries.flatten() {
-                if let Ok(metadata) = entry.metadata() {
-                    let size = if metadata.is_file() {
-                        metadata.len()
-                    } else if metadata.is_dir() {
-                        get_dir_size(&entry.path())
-                    } else {
-                        0
-                    };
-
-                    if size > 0 {
-                        files.push(TempFile {
-                            path: entry.path().to_string_lossy().to_string(),
-                            name: entry.file_name().to_string_lossy().to_string(),
-                            size,
-                            category: "Relatórios de Erro do Windows".to_string(),
-                            root_path: root_path.clone(),
-                        });
-                        total_size += size;
-                    }
-                }
-            }
+#[derive(Debug, Serialize)]
+pub struct CategoryInfo {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    /// Browser profile, for categories listed once per profile.
+    pub profile: Option<String>,
+}
+
+/// Categories of the loaded rules, named and described in the current locale.
+#[tauri::command]
+fn list_categories(
+    app: tauri::AppHandle,
+    i18n: tauri::State<'_, I18n>,
+) -> Result<Vec<CategoryInfo>, String> {
+    let mut registry = load_rules(&app)?;
+    let catalog = i18n.catalog();
+    registry.localize(catalog);
+    let mut categories: Vec<CategoryInfo> = Vec::new();
+
+    for rule in registry.rules() {
+        if categories
+            .iter()
+            .any(|c| c.id == rule.category && c.profile == rule.profile)
+        {
+            continue;
         }
+        categories.push(CategoryInfo {
+            id: rule.category.clone(),
+            name: rule.category_name().to_string(),
+            description: catalog
+                .category(&rule.category)
+                .map(|t| t.description.clone())
+                .unwrap_or_default(),
+            profile: rule.profile.clone(),
+        });
     }
 
-    // Browser caches and additional caches
-    if let Ok(local_app_data) = std::env::var("LOCALAPPDATA") {
-        let browser_caches = vec![
-            (
-                format!(
-                    "{}\\Google\\Chrome\\User Data\\Default\\Cache",
-                    local_app_data
-                ),
-                "Cache do Chrome",
-            ),
-            (
-                format!(
-                    "{}\\Microsoft\\Edge\\User Data\\Default\\Cache",
-                    local_app_data
-                ),
-                "Cache do Edge",
-            ),
-            (
-                format!("{}\\Mozilla\\Firefox\\Profiles", local_app_data),
-                "Cache do Firefox",
-            ),
-            (
-                format!("{}\\Microsoft\\Windows\\INetCache", local_app_data),
-                "Cache do Internet Explorer",
-            ),
-            (
-                format!("{}\\Microsoft\\Windows\\Explorer", local_app_data),
-                "Miniaturas do Explorer",
-            ),
-        ];
+    Ok(categories)
+}
 
-        for (cache_path, category) in browser_caches {
-            let path = PathBuf::from(&cache_path);
-            if path.exists() {
-                let root_path = cache_path.clone();
-                if let Ok(entries) = fs::read_dir(&path) {
-                    for entry in entries.flatten() {
-                        if let Ok(metadata) = entry.metadata() {
-                            let size = if metadata.is_file() {
-                                metadata.len()
-                            } else if metadata.is_dir() {
-                                get_dir_size(&entry.path())
-                            } else {
-                                0
-                            };
-
-                            if size > 0 {
-                                files.push(TempFile {
-                                    path: entry.path().to_string_lossy().to_string(),
-                                    name: entry.file_name().to_string_lossy().to_string(),
-                                    size,
-                                    category: category.to_string(),
-                                    root_path: root_path.clone(),
-                                });
-                                total_size += size;
-                            }
-                        }
-                    }
-                }
-            }
-        }
+fn config_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_config_dir()
+        .map_err(|e| format!("Pasta de configuração indisponível: {}", e))
+}
+
+/// Paths and globs protected from scanning and deletion.
+#[tauri::command]
+fn list_exclusions(app: tauri::AppHandle) -> Result<Vec<Exclusion>, String> {
+    Ok(Exclusions::load(Some(&config_dir(&app)?), &RootResolver::default())?.list())
+}
+
+/// Adds or replaces the exclusion for `exclusion.path` and returns the
+/// updated list.
+#[tauri::command]
+fn add_exclusion(app: tauri::AppHandle, exclusion: Exclusion) -> Result<Vec<Exclusion>, String> {
+    let dir = config_dir(&app)?;
+    let mut exclusions = Exclusions::load(Some(&dir), &RootResolver::default())?;
+    exclusions.add(exclusion)?;
+    exclusions.save(&dir)?;
+    Ok(exclusions.list())
+}
+
+/// Removes the exclusion for `path` and returns the updated list.
+#[tauri::command]
+fn remove_exclusion(app: tauri::AppHandle, path: String) -> Result<Vec<Exclusion>, String> {
+    let dir = config_dir(&app)?;
+    let mut exclusions = Exclusions::load(Some(&dir), &RootResolver::default())?;
+    if exclusions.remove(&path) {
+        exclusions.save(&dir)?;
     }
+    Ok(exclusions.list())
+}
 
-    // Application caches (Discord, Spotify, etc.)
-    if let Ok(app_data) = std::env::var("APPDATA") {
-        let app_caches = vec![
-            (format!("{}\\Discord\\Cache", app_data), "Cache do Discord"),
-            (
-                format!("{}\\Discord\\Code Cache", app_data),
-                "Code Cache do Discord",
-            ),
-            (
-                format!("{}\\Spotify\\Storage", app_data),
-                "Cache do Spotify",
-            ),
-        ];
+#[tauri::command]
+fn get_locale(i18n: tauri::State<'_, I18n>) -> Locale {
+    i18n.locale()
+}
 
-        for (cache_path, category) in app_caches {
-            let path = PathBuf::from(&cache_path);
-            if path.exists() {
-                let root_path = cache_path.clone();
-                if let Ok(entries) = fs::read_dir(&path) {
-                    for entry in entries.flatten() {
-                        if let Ok(metadata) = entry.metadata() {
-                            let size = if metadata.is_file() {
-                                metadata.len()
-                            } else if metadata.is_dir() {
-                                get_dir_size(&entry.path())
-                            } else {
-                                0
-                            };
-
-                            if size > 0 {
-                                files.push(TempFile {
-                                    path: entry.path().to_string_lossy().to_string(),
-                                    name: entry.file_name().to_string_lossy().to_string(),
-                                    size,
-                                    category: category.to_string(),
-                                    root_path: root_path.clone(),
-                                });
-                                total_size += size;
-                            }
-                        }
-                    }
-                }
-            }
-        }
+#[tauri::command]
+fn set_locale(i18n: tauri::State<'_, I18n>, locale: Locale) {
+    i18n.set_locale(locale);
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct DeleteOptions {
+    /// Delete entries a process has open.
+    pub include_in_use: bool,
+    /// Remove nothing and return the plan in `planned`.
+    pub dry_run: bool,
+    /// Move entries into the quarantine, from where they can be restored.
+    pub quarantine: bool,
+}
+
+fn data_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map_err(|e| format!("Pasta de dados indisponível: {}", e))
+}
+
+/// Waits while the CLI, another command or a cleaning run has it open.
+fn open_quarantine(app: &tauri::AppHandle) -> Result<Quarantine, String> {
+    Quarantine::open(&data_dir(app)?)
+}
+
+/// Deletes `paths` in a background job and returns its report once done.
+/// The job is announced as `delete-started`, reports `delete-progress` and
+/// ends with `delete-finished`; it outlives a window reload and is found
+/// again through `get_delete_job`.
+#[tauri::command]
+async fn delete_temp_files(
+    app: tauri::AppHandle,
+    sessions: tauri::State<'_, ScanSessions>,
+    jobs: tauri::State<'_, DeleteJobs>,
+    session_id: String,
+    paths: Vec<String>,
+    filters: Option<EntryFilters>,
+    options: Option<DeleteOptions>,
+) -> Result<DeleteReport, String> {
+    let session = sessions
+        .get(&session_id)
+        .ok_or_else(|| "Sessão de escaneamento desconhecida ou expirada".to_string())?;
+    let registry = load_rules(&app)?;
+    let options = options.unwrap_or_default();
+    let request = filters.unwrap_or_default();
+    if options.dry_run {
+        return Ok(plan_entries(
+            &registry,
+            &session,
+            &paths,
+            &request,
+            options.include_in_use,
+        ));
     }
 
-    Ok(ScanResult { files, total_size })
+    let job = DeleteJob::new(DeleteControl::observed(Arc::new(Events(app.clone()))));
+    jobs.start(job.clone());
+    let _ = app.emit("delete-started", job.status());
+
+    let run = job.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let result = run_delete(&app, &registry, &session, &paths, &request, &options, &run);
+        // Recorded here rather than by the command, whose caller may be
+        // gone after a reload.
+        run.finish(result.clone());
+        let _ = app.emit("delete-finished", run.status());
+        result
+    })
+    .await
+    .map_err(|e| format!("Erro ao excluir: {}", e))?
 }
 
-#[derive(Clone, Serialize)]
-struct DeleteProgress {
-    current: usize,
-    total: usize,
-    percentage: f32,
-    deleted_size: u64,
+fn run_delete(
+    app: &tauri::AppHandle,
+    registry: &RuleRegistry,
+    session: &ScanSession,
+    paths: &[String],
+    request: &EntryFilters,
+    options: &DeleteOptions,
+    job: &DeleteJob,
+) -> Result<DeleteReport, String> {
+    // Only runs into the quarantine wait for the commands that change it.
+    let mut quarantine = if options.quarantine {
+        Some(open_quarantine(app)?)
+    } else {
+        None
+    };
+    Ok(delete_entries(
+        registry,
+        session,
+        paths,
+        request,
+        options.include_in_use,
+        quarantine.as_mut(),
+        job.control(),
+    ))
 }
 
+/// The cleaning job with `job_id`, or the most recent one.
 #[tauri::command]
-fn delete_temp_files(app: tauri::AppHandle, paths: Vec<String>) -> Result<u64, String> {
-    let mut deleted_size = 0u64;
-    let total = paths.len();
-
-    for (index, path_str) in paths.iter().enumerate() {
-        let path = PathBuf::from(&path_str);
-        if path.exists() {
-            if let Ok(metadata) = fs::metadata(&path) {
-                let size = if metadata.is_file() {
-                    metadata.len()
-                } else {
-                    get_dir_size(&path)
-                };
-
-                let result = if metadata.is_file() {
-                    fs::remove_file(&path)
-                } else {
-                    fs::remove_dir_all(&path)
-                };
-
-                if result.is_ok() {
-                    deleted_size += size;
-                }
-            }
-        }
+fn get_delete_job(
+    jobs: tauri::State<'_, DeleteJobs>,
+    job_id: Option<String>,
+) -> Option<DeleteJobStatus> {
+    jobs.get(job_id.as_deref()).map(|job| job.status())
+}
 
-        // Emit progress event
-        let progress = DeleteProgress {
-            current: index + 1,
-            total,
-            percentage: ((index + 1) as f32 / total as f32) * 100.0,
-            deleted_size,
-        };
-        let _ = app.emit("delete-progress", progress);
-    }
+/// Stops the cleaning job with `job_id`, or every running one, before its
+/// next path. Returns false when there was nothing to cancel.
+#[tauri::command]
+fn cancel_delete(jobs: tauri::State<'_, DeleteJobs>, job_id: Option<String>) -> bool {
+    jobs.cancel(job_id.as_deref())
+}
 
-    Ok(deleted_size)
+/// Quarantined items, most recent first, and the retention in effect.
+#[tauri::command]
+fn list_quarantine(app: tauri::AppHandle) -> Result<QuarantineList, String> {
+    Quarantine::list_in(&data_dir(&app)?)
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct SystemInfo {
-    pub cpu_name: String,
-    pub cpu_cores: usize,
-    pub total_memory: u64,
-    pub used_memory: u64,
-    pub os_name: String,
-    pub os_version: String,
-    pub kernel_version: String,
+/// Runs `change` on the quarantine off the main thread, once no other
+/// command, cleaning run or CLI is changing it.
+async fn change_quarantine<T, F>(app: tauri::AppHandle, change: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut Quarantine) -> Result<T, String> + Send + 'static,
+{
+    tauri::async_runtime::spawn_blocking(move || change(&mut open_quarantine(&app)?))
+        .await
+        .map_err(|e| format!("Erro na quarentena: {}", e))?
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct DiskInfo {
-    pub name: String,
-    pub mount_point: String,
-    pub total_space: u64,
-    pub available_space: u64,
-    pub is_removable: bool,
-    pub file_system: String,
+/// Moves the items with `ids` back to their original paths.
+#[tauri::command]
+async fn restore_items(
+    app: tauri::AppHandle,
+    i18n: tauri::State<'_, I18n>,
+    ids: Vec<String>,
+) -> Result<RestoreResult, String> {
+    let catalog = i18n.catalog();
+    change_quarantine(app, move |quarantine| Ok(quarantine.restore(&OsFs, &ids, catalog))).await
 }
 
+/// Deletes the items with `ids` for good; every item when `ids` is omitted.
 #[tauri::command]
-fn get_system_info() -> Result<SystemInfo, String> {
-    let mut sys = System::new_all();
-    sys.refresh_all();
-
-    let cpu_name = sys
-        .cpus()
-        .first()
-        .map(|cpu| cpu.brand().to_string())
-        .unwrap_or_else(|| "Desconhecido".to_string());
-
-    let cpu_cores = sys.cpus().len();
-    let total_memory = sys.total_memory();
-    let used_memory = sys.used_memory();
-
-    let os_name = System::name().unwrap_or_else(|| "Desconhecido".to_string());
-    let os_version = System::os_version().unwrap_or_else(|| "Desconhecido".to_string());
-    let kernel_version = System::kernel_version().unwrap_or_else(|| "Desconhecido".to_string());
-
-    Ok(SystemInfo {
-        cpu_name,
-        cpu_cores,
-        total_memory,
-        used_memory,
-        os_name,
-        os_version,
-        kernel_version,
-    })
+async fn purge_quarantine(
+    app: tauri::AppHandle,
+    ids: Option<Vec<String>>,
+) -> Result<PurgeResult, String> {
+    change_quarantine(app, move |quarantine| Ok(quarantine.purge(&OsFs, ids.as_deref()))).await
+}
+
+/// Changes how long items are kept and purges what falls outside it.
+#[tauri::command]
+async fn set_quarantine_retention(
+    app: tauri::AppHandle,
+    retention: Retention,
+) -> Result<PurgeResult, String> {
+    change_quarantine(app, move |quarantine| quarantine.set_retention(&OsFs, retention)).await
+}
+
+#[tauri::command]
+fn get_system_info(i18n: tauri::State<'_, I18n>) -> Result<SystemInfo, String> {
+    Ok(system_info(i18n.catalog()))
 }
 
 #[tauri::command]
 fn get_disk_info() -> Result<Vec<DiskInfo>, String> {
-    let disks = Disks::new_with_refreshed_list();
-    let mut disk_list = Vec::new();
-
-    for disk in disks.list() {
-        disk_list.push(DiskInfo {
-            name: disk.name().to_string_lossy().to_string(),
-            mount_point: disk.mount_point().to_string_lossy().to_string(),
-            total_space: disk.total_space(),
-            available_space: disk.available_space(),
-            is_removable: disk.is_removable(),
-            file_system: disk.file_system().to_string_lossy().to_string(),
-        });
+    Ok(disk_info())
+}
+
+/// Writes a report of a scan or a cleaning run to `path`, with the host and
+/// disk state at export time.
+#[tauri::command]
+fn export_scan_report(
+    i18n: tauri::State<'_, I18n>,
+    path: String,
+    format: ReportFormat,
+    source: ReportSource,
+) -> Result<(), String> {
+    let path = PathBuf::from(path);
+    if !path.is_absolute() {
+        return Err(format!("Caminho inválido: {}", path.display()));
+    }
+    let catalog = i18n.catalog();
+    let report = Report::new(source, system_info(catalog), disk_info());
+    report.write(&path, format, catalog)
+}
+
+/// Size tree of `query.path` for a treemap. The walk is kept, so later
+/// calls for folders inside it are answered without touching the disk unless
+/// `query.refresh` is set. Reports `scan-progress` and stops on `cancel_scan`.
+#[tauri::command]
+async fn analyze_path(
+    app: tauri::AppHandle,
+    jobs: tauri::State<'_, ScanJobs>,
+    cache: tauri::State<'_, UsageCache>,
+    query: UsageQuery,
+) -> Result<UsageNode, String> {
+    let path = PathBuf::from(&query.path);
+    if !path.is_absolute() || !path.is_dir() {
+        return Err(format!("Pasta não encontrada: {}", query.path));
+    }
+    if !query.refresh {
+        if let Some(node) = cache.node(&path, query.depth, query.top_n) {
+            return Ok(node);
+        }
     }
 
-    Ok(disk_list)
+    let control = Arc::new(ScanControl::observed(Arc::new(Events(app.clone()))));
+    jobs.start(control.clone());
+
+    let scan_control = control.clone();
+    let walk_path = path.clone();
+    let tree = tauri::async_runtime::spawn_blocking(move || {
+        scan_control.begin(&walk_path.to_string_lossy(), "disk-usage", &walk_path.to_string_lossy());
+        DirTree::build(&walk_path, &scan_control)
+    })
+    .await;
+    jobs.finish(&control);
+
+    let tree = tree.map_err(|e| format!("Erro ao analisar: {}", e))?;
+    let node = tree.node(&path, query.depth, query.top_n);
+    // A cancelled walk is incomplete and not worth drilling into.
+    if !control.is_cancelled() {
+        cache.insert(path, Arc::new(tree));
+    }
+    Ok(node)
 }
 
 #[tauri::command]
@@ -532,18 +613,12 @@ pub struct MemoryOptimizationResult {
     pub is_admin: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct ProcessInfo {
-    pub pid: u32,
-    pub name: String,
-    pub cpu_usage: f32,
-    pub memory: u64,
-    pub disk_usage: u64,
-    pub status: String,
-}
-
 #[tauri::command]
-async fn optimize_memory() -> Result<MemoryOptimizationResult, String> {
+async fn optimize_memory(
+    i18n: tauri::State<'_, I18n>,
+) -> Result<MemoryOptimizationResult, String> {
+    let catalog = i18n.catalog();
+    #[cfg(target_os = "windows")]
     use std::process::Command;
     
     let mut sys = System::new_all();
@@ -553,7 +628,7 @@ async fn optimize_memory() -> Result<MemoryOptimizationResult, String> {
     let mut success_count = 0;
     
     #[cfg(target_os = "windows")]
-    let is_admin = is_elevated();
+    let is_admin = zcleaner_core::is_elevated();
     
     #[cfg(not(target_os = "windows"))]
     let is_admin = false;
@@ -611,7 +686,7 @@ async fn optimize_memory() -> Result<MemoryOptimizationResult, String> {
     #[cfg(not(target_os = "windows"))]
     let total_operations = {
         // For non-Windows systems, just do basic cleanup
-        success_count = 1;
+        success_count += 1;
         tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
         1
     };
@@ -624,20 +699,22 @@ async fn optimize_memory() -> Result<MemoryOptimizationResult, String> {
     sys.refresh_memory();
     let after_used = sys.used_memory();
     
-    let freed = if before_used > after_used {
-        before_used - after_used
-    } else {
-        0
-    };
+    let freed = before_used.saturating_sub(after_used);
     
     let message = if !is_admin {
-        "Otimização básica executada - execute como administrador para melhores resultados".to_string()
+        catalog.message("memory-not-admin")
     } else if success_count == total_operations {
-        "Memória otimizada com sucesso".to_string()
+        catalog.message("memory-success")
     } else if success_count > 0 {
-        format!("Otimização parcial: {} de {} operações concluídas", success_count, total_operations)
+        catalog.format(
+            "memory-partial",
+            &[
+                ("done", success_count.to_string()),
+                ("total", total_operations.to_string()),
+            ],
+        )
     } else {
-        "Não foi possível otimizar a memória".to_string()
+        catalog.message("memory-failed")
     };
     
     Ok(MemoryOptimizationResult {
@@ -651,63 +728,49 @@ async fn optimize_memory() -> Result<MemoryOptimizationResult, String> {
 }
 
 #[tauri::command]
-fn get_processes() -> Result<Vec<ProcessInfo>, String> {
-    let mut sys = System::new_all();
-    sys.refresh_all();
-    
-    let cpu_count = sys.cpus().len() as f32;
-    let mut processes = Vec::new();
-    
-    for (pid, process) in sys.processes() {
-        let status = if process.status().to_string().contains("Run") {
-            "Executando".to_string()
-        } else if process.status().to_string().contains("Sleep") {
-            "Suspenso".to_string()
-        } else {
-            process.status().to_string()
-        };
-        
-        // Normalizar CPU usage dividindo pelo número de núcleos
-        let cpu_usage = process.cpu_usage() / cpu_count;
-        
-        processes.push(ProcessInfo {
-            pid: pid.as_u32(),
-            name: process.name().to_string_lossy().to_string(),
-            cpu_usage,
-            memory: process.memory(),
-            disk_usage: process.disk_usage().total_read_bytes + process.disk_usage().total_written_bytes,
-            status,
-        });
-    }
-    
-    Ok(processes)
+fn get_processes(i18n: tauri::State<'_, I18n>) -> Result<Vec<ProcessInfo>, String> {
+    Ok(list_processes(i18n.catalog()))
 }
 
 #[tauri::command]
-fn kill_process(pid: u32) -> Result<bool, String> {
-    let mut sys = System::new_all();
-    sys.refresh_all();
-    
-    if let Some(process) = sys.process(sysinfo::Pid::from_u32(pid)) {
-        if process.kill() {
-            Ok(true)
-        } else {
-            Err("Não foi possível encerrar o processo".to_string())
-        }
-    } else {
-        Err("Processo não encontrado".to_string())
-    }
+fn kill_process(i18n: tauri::State<'_, I18n>, pid: u32) -> Result<bool, String> {
+    terminate_process(i18n.catalog(), pid)
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
+        .manage(ScanJobs::default())
+        .manage(ScanSessions::default())
+        .manage(Sessions::<DuplicateSession>::default())
+        .manage(UsageCache::default())
+        .manage(I18n::default())
+        .manage(DeleteJobs::default())
         .invoke_handler(tauri::generate_handler![
             scan_temp_files,
+            find_build_artifacts,
+            find_duplicates,
+            resolve_duplicates,
+            cancel_scan,
+            list_cleaning_rules,
+            list_categories,
+            list_exclusions,
+            add_exclusion,
+            remove_exclusion,
+            get_locale,
+            set_locale,
             delete_temp_files,
+            get_delete_job,
+            cancel_delete,
+            list_quarantine,
+            restore_items,
+            purge_quarantine,
+            set_quarantine_retention,
             get_system_info,
             get_disk_info,
+            export_scan_report,
+            analyze_path,
             open_folder_location,
             optimize_memory,
             get_processes,
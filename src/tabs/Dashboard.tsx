@@ -18,15 +18,136 @@ interface TempFile {
   path: string;
   name: string;
   size: number;
+  kind: 'file' | 'dir' | 'link';
+  category_id: string;
   category: string;
   root_path: string;
+  modified: number | null;
+  accessed: number | null;
+  created: number | null;
+  in_use: { pid: number; name: string }[];
+}
+
+interface ScanProgress {
+  rule_id: string;
+  category_id: string;
+  category: string;
+  rules_done: number;
+  rules_total: number;
+  entries_visited: number;
+  bytes_counted: number;
+}
+
+interface ScanPartial {
+  rule_id: string;
+  category_id: string;
+  category: string;
+  files: TempFile[];
+  total_size: number;
 }
 
 interface ScanResult {
+  session_id: string;
   files: TempFile[];
   total_size: number;
+  cancelled: boolean;
+  scanned_at: number | null;
 }
 
+type Outcome = 'deleted' | 'partially_deleted' | 'skipped' | 'failed';
+
+type Rejection =
+  | 'not_scanned'
+  | 'unknown_root'
+  | 'outside_root'
+  | 'missing'
+  | 'changed'
+  | 'filtered'
+  | 'in_use'
+  | 'excluded';
+
+type FailureKind =
+  | 'permission_denied'
+  | 'read_only'
+  | 'in_use'
+  | 'not_found'
+  | 'excluded'
+  | 'mount_point'
+  | 'other';
+
+interface ItemReport {
+  path: string;
+  outcome: Outcome;
+  error: FailureKind | null;
+  rejection: Rejection | null;
+  files_removed: number;
+  bytes_freed: number;
+  failures: { path: string; kind: FailureKind }[];
+}
+
+interface DeleteReport {
+  deleted_size: number;
+  deleted: TempFile[];
+  rejected: { path: string; reason: Rejection }[];
+  items: ItemReport[];
+  freed_bytes: number;
+  removed_files: number;
+  finished_at: number | null;
+  quarantined: boolean;
+  cancelled: boolean;
+  dry_run: boolean;
+  planned: PlannedEntry[];
+}
+
+interface PlannedEntry {
+  entry: TempFile;
+  removal: {
+    files: number;
+    dirs: number;
+    links: number;
+    bytes: number;
+    failed: number;
+    failures: { path: string; kind: FailureKind }[];
+  };
+}
+
+const OUTCOME_LABELS: Record<Outcome, string> = {
+  deleted: 'Removido',
+  partially_deleted: 'Removido em parte',
+  skipped: 'Ignorado',
+  failed: 'Não removido',
+};
+
+const REJECTION_LABELS: Record<Rejection, string> = {
+  not_scanned: 'não faz parte da varredura',
+  unknown_root: 'a regra não está mais ativa',
+  outside_root: 'fora da pasta da regra',
+  missing: 'não existe mais',
+  changed: 'mudou desde a varredura',
+  filtered: 'não passa mais nos filtros',
+  in_use: 'em uso por um processo',
+  excluded: 'protegido por uma exclusão',
+};
+
+const FAILURE_LABELS: Record<FailureKind, string> = {
+  permission_denied: 'permissão negada',
+  read_only: 'somente leitura',
+  in_use: 'em uso por um processo',
+  not_found: 'não encontrado',
+  excluded: 'protegido por uma exclusão',
+  mount_point: 'ponto de montagem preservado',
+  other: 'erro inesperado',
+};
+
+const describeItem = (item: ItemReport) => {
+  const reason = item.rejection
+    ? REJECTION_LABELS[item.rejection]
+    : item.error
+      ? FAILURE_LABELS[item.error]
+      : null;
+  return reason ? `${OUTCOME_LABELS[item.outcome]}: ${reason}` : OUTCOME_LABELS[item.outcome];
+};
+
 const getPageVariants = (shouldReduceMotion: boolean) => ({
   initial: { opacity: shouldReduceMotion ? 1 : 0, y: shouldReduceMotion ? 0 : 20 },
   animate: {
@@ -55,12 +176,15 @@ export default function Dashboard() {
   const [isDialogOpen, setIsDialogOpen] = useState(false);
   const [isScanning, setIsScanning] = useState(false);
   const [isDeleting, setIsDeleting] = useState(false);
+  const [scanProgress, setScanProgress] = useState<ScanProgress | null>(null);
+  const [scanFound, setScanFound] = useState({ count: 0, size: 0 });
   const [scanResult, setScanResult] = useState<ScanResult | null>(null);
   const [selectedFiles, setSelectedFiles] = useState<Set<string>>(new Set());
   const [collapsedCategories, setCollapsedCategories] = useState<Set<string>>(new Set());
   const [deleteProgress, setDeleteProgress] = useState(0);
   const [deleteComplete, setDeleteComplete] = useState(false);
   const [deletedSize, setDeletedSize] = useState(0);
+  const [unfinishedItems, setUnfinishedItems] = useState<ItemReport[]>([]);
 
   // Estados para otimização de memória
   const [isOptimizingMemory, setIsOptimizingMemory] = useState(false);
@@ -74,6 +198,8 @@ export default function Dashboard() {
     setIsScanning(true);
     setIsDialogOpen(true);
     setDeleteComplete(false);
+    setScanProgress(null);
+    setScanFound({ count: 0, size: 0 });
 
     try {
       const result = await invoke<ScanResult>('scan_temp_files');
@@ -182,11 +308,23 @@ export default function Dashboard() {
     let unlisten: (() => void) | undefined;
 
     const setupListener = async () => {
-      unlisten = await listen<{ current: number; total: number; percentage: number; deleted_size: number }>(
+      unlisten = await listen<{
+        current: number;
+        total: number;
+        percentage: number;
+        deleted_size: number;
+        freed_bytes: number;
+        bytes_done: number;
+        bytes_total: number;
+        removed_files: number;
+        files_per_second: number;
+        eta_seconds: number | null;
+        item: ItemReport;
+      }>(
         'delete-progress',
         (event) => {
           setDeleteProgress(Math.round(event.payload.percentage));
-          setDeletedSize(event.payload.deleted_size);
+          setDeletedSize(event.payload.freed_bytes);
         }
       );
     };
@@ -198,19 +336,49 @@ export default function Dashboard() {
     };
   }, []);
 
+  useEffect(() => {
+    let unlistenProgress: (() => void) | undefined;
+    let unlistenPartial: (() => void) | undefined;
+
+    const setupListeners = async () => {
+      unlistenProgress = await listen<ScanProgress>('scan-progress', (event) => {
+        setScanProgress(event.payload);
+      });
+      unlistenPartial = await listen<ScanPartial>('scan-partial', (event) => {
+        setScanFound(found => ({
+          count: found.count + event.payload.files.length,
+          size: found.size + event.payload.total_size,
+        }));
+      });
+    };
+
+    setupListeners();
+
+    return () => {
+      if (unlistenProgress) unlistenProgress();
+      if (unlistenPartial) unlistenPartial();
+    };
+  }, []);
+
   const handleDelete = async () => {
-    if (selectedFiles.size === 0) return;
+    if (selectedFiles.size === 0 || !scanResult) return;
 
     setIsDeleting(true);
     setDeleteProgress(0);
     setDeletedSize(0);
+    setUnfinishedItems([]);
 
     try {
       const pathsToDelete = Array.from(selectedFiles);
-      const result = await invoke<number>('delete_temp_files', { paths: pathsToDelete });
+      const result = await invoke<DeleteReport>('delete_temp_files', {
+        sessionId: scanResult.session_id,
+        paths: pathsToDelete,
+      });
 
       setDeleteProgress(100);
-      setDeletedSize(result);
+      setDeletedSize(result.freed_bytes);
+      // Refused paths are in `items` too, as skipped.
+      setUnfinishedItems(result.items.filter((item) => item.outcome !== 'deleted'));
       setDeleteComplete(true);
     } catch (error) {
       console.error('Erro ao deletar:', error);
@@ -605,12 +773,33 @@ export default function Dashboard() {
           </DialogHeader>
 
           {isScanning ? (
-            <div className="flex flex-col items-center justify-center py-12">
-              <LoadingState
-                type="spinner"
-                size="lg"
-                message="Escaneando arquivos temporários..."
-              />
+            <div className="flex flex-col items-center justify-center gap-4 py-12 px-6">
+              {scanProgress && scanProgress.rules_total > 0 ? (
+                <LoadingState
+                  type="progress"
+                  size="md"
+                  progress={Math.round((scanProgress.rules_done / scanProgress.rules_total) * 100)}
+                  message={`Escaneando ${scanProgress.category}... (${scanProgress.rules_done}/${scanProgress.rules_total})`}
+                />
+              ) : (
+                <LoadingState
+                  type="spinner"
+                  size="lg"
+                  message="Escaneando arquivos temporários..."
+                />
+              )}
+              {scanFound.count > 0 && (
+                <span className="text-[var(--color-text-tertiary)] text-xs">
+                  {scanFound.count} itens encontrados até agora ({formatBytes(scanFound.size)})
+                </span>
+              )}
+              <AnimatedButton
+                onClick={() => invoke('cancel_scan')}
+                variant="outline"
+                size="sm"
+              >
+                Parar
+              </AnimatedButton>
             </div>
           ) : deleteComplete ? (
             <motion.div
@@ -626,6 +815,35 @@ export default function Dashboard() {
                 size="lg"
                 mono={true}
               />
+              {unfinishedItems.length > 0 && (
+                <div className="w-full px-6 space-y-2">
+                  <p className="text-[var(--color-text-secondary)] text-xs">
+                    {unfinishedItems.length === 1
+                      ? '1 item não foi removido por completo'
+                      : `${unfinishedItems.length} itens não foram removidos por completo`}
+                  </p>
+                  <div className="overflow-y-auto max-h-[200px] space-y-1">
+                    {unfinishedItems.map((item) => (
+                      <div
+                        key={item.path}
+                        className="flex items-center gap-3 p-2 rounded border border-[var(--color-border-default)] bg-[var(--color-bg-base)]"
+                      >
+                        <div className="flex-1 min-w-0">
+                          <p className="text-[var(--color-text-secondary)] text-xs truncate" title={item.path}>
+                            {item.path}
+                          </p>
+                          <p className="text-[var(--color-text-tertiary)] text-xs">{describeItem(item)}</p>
+                        </div>
+                        {item.bytes_freed > 0 && (
+                          <span className="text-[var(--color-text-tertiary)] text-xs font-mono">
+                            {formatBytes(item.bytes_freed)}
+                          </span>
+                        )}
+                      </div>
+                    ))}
+                  </div>
+                </div>
+              )}
             </motion.div>
           ) : (
             <>
@@ -760,6 +978,7 @@ export default function Dashboard() {
                     setDeleteProgress(0);
                     setDeleteComplete(false);
                     setDeletedSize(0);
+                    setUnfinishedItems([]);
                   }}
                   variant="primary"
                   size="md"
@@ -784,8 +1003,9 @@ export default function Dashboard() {
                     <AnimatedButton
                       variant="outline"
                       size="md"
-                      onClick={() => setIsDialogOpen(false)}
-                      disabled={isDeleting}
+                      onClick={() =>
+                        isDeleting ? invoke('cancel_delete') : setIsDialogOpen(false)
+                      }
                     >
                       Cancelar
                     </AnimatedButton>
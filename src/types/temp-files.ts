@@ -3,12 +3,21 @@ export interface TempFile {
   path: string;
   name: string;
   size: number;
+  kind: 'file' | 'dir' | 'link';
+  category_id: string;
   category: string;
+  modified: number | null;
+  accessed: number | null;
+  created: number | null;
+  in_use: { pid: number; name: string }[];
 }
 
 export interface ScanResult {
+  session_id: string;
   files: TempFile[];
   total_size: number;
+  cancelled: boolean;
+  scanned_at: number | null;
 }
 
 export type CleaningState = 'idle' | 'scanning' | 'selecting' | 'deleting' | 'complete';